@@ -0,0 +1,38 @@
+-- Up
+
+PRAGMA foreign_keys=OFF;
+
+-- where each speciesplace row came from. the USDA and VASCAN exports write a row for every range link that they
+-- assert, a curator can record one with the source 'manual'. a link can be asserted by more than one source.
+CREATE TABLE speciesplacesource (
+    species_id INTEGER NOT NULL,
+    place_id   INTEGER NOT NULL,
+    source     TEXT    NOT NULL CHECK (source IN ('usda', 'vascan', 'manual')),
+    -- the USDA snapshot id or the VASCAN API version
+    version    TEXT    NOT NULL DEFAULT '',
+    -- when the source data is from: the date the USDA CSVs were downloaded or the date VASCAN was last updated
+    asof       TEXT    NOT NULL DEFAULT '',
+    -- when the row was written
+    recorded   TEXT    NOT NULL,
+    -- the raw record(s) from the source that assert the link
+    record     TEXT    NOT NULL DEFAULT '',
+    FOREIGN KEY (species_id, place_id) REFERENCES speciesplace (species_id, place_id) ON DELETE CASCADE,
+    PRIMARY KEY (species_id, place_id, source)
+);
+
+-- there is no telling which of the links that are already here were entered by curators and which were added by an
+-- earlier import, so they are all taken to be the curators'. an importer that asserts one of them is recorded beside
+-- 'manual', and the importers never remove a link that a curator asserts.
+INSERT INTO speciesplacesource (species_id, place_id, source, recorded)
+    SELECT species_id, place_id, 'manual', strftime('%Y%m%dT%H%M%SZ', 'now')
+      FROM speciesplace;
+
+PRAGMA foreign_keys=ON;
+
+--------------------------------------------------------------
+-- Down
+PRAGMA foreign_keys=OFF;
+
+DROP TABLE speciesplacesource;
+
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,22 @@
+-- Up
+
+PRAGMA foreign_keys=OFF;
+
+-- whether a host is native to a place: 'native', 'introduced' or 'invasive', NULL if it is not known. the importer sets
+-- it from VASCAN, USDA's state checklists do not say. VASCAN does not say which plants are invasive, so 'invasive' is
+-- only set by curators and the importer never changes it.
+ALTER TABLE speciesplace ADD COLUMN nativity TEXT DEFAULT NULL CHECK (nativity IN ('native', 'introduced', 'invasive'));
+
+-- what each source said about it
+ALTER TABLE speciesplacesource ADD COLUMN nativity TEXT DEFAULT NULL CHECK (nativity IN ('native', 'introduced', 'invasive'));
+
+PRAGMA foreign_keys=ON;
+
+--------------------------------------------------------------
+-- Down
+PRAGMA foreign_keys=OFF;
+
+ALTER TABLE speciesplacesource DROP COLUMN nativity;
+ALTER TABLE speciesplace DROP COLUMN nativity;
+
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,22 @@
+-- Up
+
+PRAGMA foreign_keys=OFF;
+
+-- VASCAN's occurrence status for a host in a place, e.g., 'doubtful', NULL if the sources that assert it do not give one.
+-- which statuses are exported as range is up to the export, this records which one each link has so that e.g. doubtful
+-- records can be shown differently.
+ALTER TABLE speciesplace ADD COLUMN occurrence TEXT DEFAULT NULL CHECK (occurrence IN ('present', 'native', 'introduced', 'ephemeral', 'doubtful', 'excluded', 'extirpated'));
+
+-- what each source said
+ALTER TABLE speciesplacesource ADD COLUMN occurrence TEXT DEFAULT NULL CHECK (occurrence IN ('present', 'native', 'introduced', 'ephemeral', 'doubtful', 'excluded', 'extirpated'));
+
+PRAGMA foreign_keys=ON;
+
+--------------------------------------------------------------
+-- Down
+PRAGMA foreign_keys=OFF;
+
+ALTER TABLE speciesplacesource DROP COLUMN occurrence;
+ALTER TABLE speciesplace DROP COLUMN occurrence;
+
+PRAGMA foreign_keys=ON;
@@ -6,46 +6,151 @@
 PRAGMA foreign_keys = off;
 BEGIN TRANSACTION;
 
-CREATE TABLE alias (
+CREATE TABLE IF NOT EXISTS alias (
     id   INTEGER PRIMARY KEY AUTOINCREMENT,
     name TEXT    NOT NULL UNIQUE
 );
 
-CREATE TABLE plant (
+CREATE TABLE IF NOT EXISTS plant (
     id            INTEGER PRIMARY KEY AUTOINCREMENT,
-    rawname       TEXT    NOT NULL UNIQUE,
+    rawname       TEXT    NOT NULL,
     symbol        TEXT    NOT NULL,
     symbolsynonym TEXT    NOT NULL DEFAULT "",
     family        TEXT    NOT NULL,
     genus         TEXT    NOT NULL,
     specific      TEXT    NOT NULL,
-    type          TEXT    NOT NULL CHECK (type IN ("ssp.", "var.", "sp.", "x") ),
+    type          TEXT    NOT NULL CHECK (type IN ("ssp.", "var.", "subvar.", "f.", "subf.", "sp.", "x") ),
     sspvar        TEXT    NOT NULL DEFAULT "",
-    hybridpair    TEXT    NOT NULL DEFAULT "",
+    -- the ranks between the species and the rank of the plant for quadrinomials, e.g., "ssp. americana"
+    parentranks   TEXT    NOT NULL DEFAULT "",
+    -- 1 if the genus is a nothogenus, e.g., ×Achnella, or the species a nothospecies, e.g., Quercus ×beadlei
+    nothogenus    INTEGER NOT NULL DEFAULT 0,
+    nothospecies  INTEGER NOT NULL DEFAULT 0,
+    -- the hybrid formula in the brackets after the name, e.g., "alba × michauxii", or for an unnamed hybrid the note
+    formula       TEXT    NOT NULL DEFAULT "",
     author        TEXT    NOT NULL,
-    secondauthor  TEXT    NOT NULL DEFAULT ""
+    secondauthor  TEXT    NOT NULL DEFAULT "",
+    -- for a synonym, the accepted taxon that USDA lists it under, i.e., the plant with the same symbol and no synonym
+    -- symbol. NULL for accepted taxa.
+    accepted_id   INTEGER REFERENCES plant (id) ON DELETE SET NULL,
+    status        TEXT    NOT NULL DEFAULT "" CHECK (status IN ("", "orth. var.", "orth. cons.", "orth. rej.", "nom. cons.", "nom. rej.", "nom. utique rej.", "nom. inval.", "nom. illeg.", "nom. nud.", "nom. inq.", "nom. dub.", "nom. obsc.", "nom. ambig.", "excl. type") ),
+    UNIQUE (symbol, rawname)
 );
 
-CREATE TABLE plantalias (
+-- names with a nomenclatural status, e.g., nom. illeg., along with the accepted taxon that USDA lists them under
+CREATE VIEW IF NOT EXISTS plantstatus AS
+    SELECT p.id AS plant_id,
+           p.rawname,
+           p.status,
+           a.id AS accepted_id,
+           a.rawname AS accepted_name
+      FROM plant AS p
+           LEFT JOIN
+           plant AS a ON (a.symbol = p.symbol AND a.symbolsynonym = '')
+     WHERE p.status != '';
+
+-- the parents of a hybrid, in the order they are given in its formula. parent_id is the accepted plant with the parent's
+-- name if there is one.
+CREATE TABLE IF NOT EXISTS plantparent (
+    plant_id    INTEGER REFERENCES plant (id) ON DELETE CASCADE
+                NOT NULL,
+    position    INTEGER NOT NULL,
+    genus       TEXT    NOT NULL,
+    specific    TEXT    NOT NULL,
+    type        TEXT    NOT NULL,
+    sspvar      TEXT    NOT NULL DEFAULT "",
+    parentranks TEXT    NOT NULL DEFAULT "",
+    parent_id   INTEGER REFERENCES plant (id) ON DELETE SET NULL,
+    PRIMARY KEY (plant_id, position)
+);
+
+-- region_id is the state whose checklist gave the plant the name. USDA's common names are per state, so a plant can
+-- have a different name in each state.
+CREATE TABLE IF NOT EXISTS plantalias (
     plant_id  INTEGER REFERENCES plant (id) ON DELETE CASCADE,
     alias_id  INTEGER REFERENCES alias (id) ON DELETE CASCADE,
-    type      TEXT NOT NULL CHECK (type IN ("common", "orth. var.") )
+    type      TEXT NOT NULL CHECK (type IN ("common", "orth. var.") ),
+    region_id INTEGER REFERENCES region (id) ON DELETE CASCADE
+              NOT NULL,
+    UNIQUE (plant_id, alias_id, type, region_id)
 );
 
-CREATE TABLE plantregion (
+-- the common names of each plant along with the state that uses them
+CREATE VIEW IF NOT EXISTS plantcommonname AS
+    SELECT p.id AS plant_id,
+           p.rawname,
+           r.code AS region_code,
+           r.name AS region_name,
+           a.name
+      FROM plantalias AS pa
+           INNER JOIN
+           plant AS p ON (p.id = pa.plant_id)
+           INNER JOIN
+           alias AS a ON (a.id = pa.alias_id)
+           INNER JOIN
+           region AS r ON (r.id = pa.region_id)
+     WHERE pa.type = 'common';
+
+CREATE TABLE IF NOT EXISTS plantregion (
     plant_id   INTEGER REFERENCES plant (id) ON DELETE CASCADE
                NOT NULL,
     region_id  INTEGER REFERENCES region (id) ON DELETE CASCADE
-               NOT NULL
+               NOT NULL,
+    PRIMARY KEY (plant_id, region_id)
 );
 
-CREATE TABLE region (
+CREATE TABLE IF NOT EXISTS region (
     id   INTEGER PRIMARY KEY AUTOINCREMENT
                  NOT NULL,
     name TEXT    NOT NULL,
-    code TEXT    NOT NULL
+    code TEXT    NOT NULL UNIQUE
+);
+
+-- every import is recorded as a snapshot of the USDA checklist so that two downloads can be compared
+CREATE TABLE IF NOT EXISTS snapshot (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT
+                       NOT NULL,
+    imported   TEXT    NOT NULL,
+    downloaded TEXT    NOT NULL
 );
 
+CREATE TABLE IF NOT EXISTS snapshotfile (
+    snapshot_id  INTEGER REFERENCES snapshot (id) ON DELETE CASCADE
+                         NOT NULL,
+    region_code  TEXT    NOT NULL,
+    filename     TEXT    NOT NULL,
+    sha256       TEXT    NOT NULL,
+    rows         INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, region_code)
+);
+
+-- the raw checklist rows, as they were in the CSVs, for each snapshot
+CREATE TABLE IF NOT EXISTS snapshotrecord (
+    snapshot_id   INTEGER REFERENCES snapshot (id) ON DELETE CASCADE
+                          NOT NULL,
+    region_code   TEXT    NOT NULL,
+    symbol        TEXT    NOT NULL,
+    symbolsynonym TEXT    NOT NULL DEFAULT "",
+    rawname       TEXT    NOT NULL,
+    family        TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS snapshotrecord_snapshot ON snapshotrecord (snapshot_id);
+
+-- every place in the VASCAN distribution of each gallformers host as of the latest VASCAN import, whatever its
+-- occurrence status. which of the statuses count as range is decided when it is exported.
+CREATE TABLE IF NOT EXISTS vascandistribution (
+    host           TEXT    NOT NULL,
+    taxon_id       INTEGER NOT NULL,
+    scientificname TEXT    NOT NULL,
+    location_id    TEXT    NOT NULL,
+    locality       TEXT    NOT NULL,
+    establishment  TEXT    NOT NULL DEFAULT "",
+    occurrence     TEXT    NOT NULL,
+    apiversion     TEXT    NOT NULL,
+    lastupdated    TEXT    NOT NULL,
+    PRIMARY KEY (host, locality)
+);
 
 COMMIT TRANSACTION;
 PRAGMA foreign_keys = on;
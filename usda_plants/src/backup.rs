@@ -0,0 +1,164 @@
+use crate::cli::Paths;
+use crate::util::timestamp;
+use crate::Res;
+use rusqlite::{Connection, MAIN_DB};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// the file name prefix used for all backups of the given DB, e.g., `gallformers-` for `gallformers.sqlite`
+fn backup_prefix(db: &Path) -> String {
+    let stem = db
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "db".to_string());
+    format!("{}-", stem)
+}
+
+/// the path for a new backup of the given DB taken at the given time. the timestamp only has a resolution of a second
+/// so a backup that is taken in the same second as an earlier one gets a numbered suffix, e.g., `_01`, rather than
+/// overwriting it. the suffix sorts after the unnumbered name.
+fn backup_path(db: &Path, backup_dir: &Path, ts: &str) -> PathBuf {
+    let ext = db
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "sqlite".to_string());
+    let mut p = backup_dir.join(format!("{}{}.{}", backup_prefix(db), ts, ext));
+    let mut n = 0;
+    while p.exists() {
+        n += 1;
+        p = backup_dir.join(format!("{}{}_{:02}.{}", backup_prefix(db), ts, n, ext));
+    }
+    p
+}
+
+/// takes an online backup of the DB open on the connection into the backup directory. the backup is named after the
+/// DB file and the current UTC time so that backups sort oldest to newest. an existing backup is never overwritten.
+/// returns the path of the new backup.
+pub fn backup(conn: &Connection, db: &Path, backup_dir: &Path) -> Res<PathBuf> {
+    fs::create_dir_all(backup_dir)?;
+    let p = backup_path(db, backup_dir, &timestamp());
+    conn.backup(MAIN_DB, &p, None)?;
+    println!("Backed up {:?} to {:?}.", db, p);
+    Ok(p)
+}
+
+/// lists all of the backups of the given DB, oldest first.
+pub fn list_backups(db: &Path, backup_dir: &Path) -> Res<Vec<PathBuf>> {
+    let prefix = backup_prefix(db);
+    let mut backups = Vec::new();
+    if backup_dir.is_dir() {
+        for entry in backup_dir.read_dir()? {
+            let p = entry?.path();
+            let is_backup = p
+                .file_name()
+                .map(|n| n.to_string_lossy().starts_with(&prefix))
+                .unwrap_or(false);
+            if is_backup {
+                backups.push(p);
+            }
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// restores the given backup over the gallformers DB. the current DB is itself backed up first so that a restore can
+/// always be undone. if no backup is given the available backups are listed instead.
+pub fn restore(paths: &Paths, from: Option<&Path>) -> Res<()> {
+    let from = match from {
+        Some(f) => f,
+        None => {
+            let backups = list_backups(&paths.gf_db, &paths.backup_dir)?;
+            if backups.is_empty() {
+                println!(
+                    "There are no backups of {:?} in {:?}.",
+                    paths.gf_db, paths.backup_dir
+                );
+            } else {
+                println!("Backups of {:?}, oldest first:", paths.gf_db);
+                for b in backups {
+                    println!("  {}", b.display());
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    if !from.is_file() {
+        return Err(format!("The backup {:?} does not exist.", from).into());
+    }
+
+    let mut gf_c = Connection::open(&paths.gf_db)?;
+    backup(&gf_c, &paths.gf_db, &paths.backup_dir)?;
+    gf_c.restore(MAIN_DB, from, None::<fn(_)>)?;
+    println!("Restored {:?} from {:?}.", paths.gf_db, from);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            backup_path(
+                Path::new("../prisma/gallformers.sqlite"),
+                Path::new("backups"),
+                "20211018T091300Z"
+            ),
+            PathBuf::from("backups/gallformers-20211018T091300Z.sqlite")
+        );
+    }
+
+    #[test]
+    fn test_backup_and_restore_in_the_same_second() {
+        let dir = std::env::temp_dir().join(format!("importer_test_backup_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let gf_db = dir.join("gallformers.sqlite");
+        let paths = Cli::try_parse_from([
+            "importer",
+            "snapshots",
+            "--gf-db",
+            gf_db.to_str().unwrap(),
+            "--backup-dir",
+            dir.join("backups").to_str().unwrap(),
+        ])
+        .unwrap()
+        .paths;
+        let conn = Connection::open(&gf_db).unwrap();
+        conn.execute_batch("CREATE TABLE t (v INTEGER); INSERT INTO t VALUES (1);")
+            .unwrap();
+        let value = |p: &Path| -> i64 {
+            Connection::open(p)
+                .unwrap()
+                .query_row("SELECT v FROM t;", [], |r| r.get(0))
+                .unwrap()
+        };
+
+        let first = backup(&conn, &gf_db, &paths.backup_dir).unwrap();
+        let second = backup(&conn, &gf_db, &paths.backup_dir).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            list_backups(&gf_db, &paths.backup_dir).unwrap(),
+            vec![first.clone(), second]
+        );
+
+        // the backup that each restore takes of the current DB must not replace the one being restored
+        conn.execute("UPDATE t SET v = 2;", []).unwrap();
+        restore(&paths, Some(&first)).unwrap();
+        assert_eq!(value(&gf_db), 1);
+        restore(&paths, Some(&first)).unwrap();
+        assert_eq!(value(&gf_db), 1);
+        assert_eq!(value(&first), 1);
+        let backups = list_backups(&gf_db, &paths.backup_dir).unwrap();
+        assert_eq!(backups.len(), 4);
+        assert_eq!(value(&backups[2]), 2);
+
+        drop(conn);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+use crate::nativity::Occurrence;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// Imports USDA and VASCAN plant data and exports host ranges into the gallformers database.
+#[derive(Debug, Parser)]
+#[command(name = "importer", version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub paths: Paths,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// the locations of all of the files that the importer reads from or writes to. all of them default to paths relative
+/// to the current directory, which matches the layout of the usda_plants directory in the gallformers repo.
+#[derive(Clone, Debug, Args)]
+pub struct Paths {
+    /// the plants database that USDA and VASCAN data is imported into
+    #[arg(long, global = true, default_value = "plants.db")]
+    pub plants_db: PathBuf,
+
+    /// the gallformers database that data is exported to
+    #[arg(long, global = true, default_value = "../prisma/gallformers.sqlite")]
+    pub gf_db: PathBuf,
+
+    /// the directory containing the USDA state CSVs
+    #[arg(long, global = true, default_value = "data")]
+    pub data_dir: PathBuf,
+
+    /// the JSON file mapping region codes to region names
+    #[arg(long, global = true, default_value = "regions.json")]
+    pub regions: PathBuf,
+
+    /// the SQL schema used to create the plants database
+    #[arg(long, global = true, default_value = "plants.sql")]
+    pub schema: PathBuf,
+
+    /// the directory that backups of the gallformers database are written to before it is changed
+    #[arg(long, global = true, default_value = "backups")]
+    pub backup_dir: PathBuf,
+
+    /// the CSV of gallformers host names and the plants database names that they are exported as
+    #[arg(long, global = true, default_value = "name_map.csv")]
+    pub name_map: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// import plant data from the given source
+    Import {
+        #[arg(value_enum)]
+        source: Source,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+    /// export previously imported plant data from the given source into the gallformers database
+    Export {
+        #[arg(value_enum)]
+        source: Source,
+
+        #[command(flatten)]
+        options: ExportOptions,
+    },
+    /// import the USDA data and then export it, in that order
+    Both {
+        #[command(flatten)]
+        import: ImportOptions,
+
+        #[command(flatten)]
+        export: ExportOptions,
+    },
+    /// check that the USDA CSVs in the data directory look complete without importing them
+    Validate,
+    /// list the snapshots of the USDA checklist that have been imported
+    Snapshots,
+    /// delete the raw records of old snapshots of the USDA checklist, they can no longer be compared afterwards
+    Prune {
+        /// how many of the most recent snapshots of each state to keep the records of
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..))]
+        keep: u32,
+    },
+    /// compare two snapshots of the USDA checklist, by default the two most recent ones
+    Diff {
+        /// the id of the older snapshot
+        old: Option<i64>,
+        /// the id of the newer snapshot
+        new: Option<i64>,
+    },
+    /// restore the gallformers database from a backup, or list the available backups if none is given
+    Restore {
+        /// the backup to restore
+        backup: Option<PathBuf>,
+    },
+    /// list the closest plants database names for the gallformers hosts that the export can not match, and add the
+    /// accepted ones to the name map
+    Reconcile(ReconcileOptions),
+    /// explain where each place in a host's range in the gallformers database came from
+    Provenance {
+        /// the name of the host, as it is in gallformers
+        host: String,
+    },
+}
+
+#[derive(Clone, Debug, Default, Args)]
+pub struct ReconcileOptions {
+    /// ask which of the candidates to accept for each host
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// add a host to the name map, e.g., --accept "Quercus albus=Quercus alba"
+    #[arg(long, value_name = "HOST=NAME")]
+    pub accept: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Args)]
+pub struct ImportOptions {
+    /// fail on the first malformed CSV record rather than quarantining it and carrying on, and refuse to import CSVs
+    /// that look incomplete rather than warning about them
+    #[arg(long)]
+    pub strict: bool,
+
+    /// where to write malformed CSV records. defaults to quarantine.csv next to the plants database
+    #[arg(long, value_name = "CSV")]
+    pub quarantine: Option<PathBuf>,
+
+    /// only import the CSV for the given state, replacing what an earlier import loaded for it. can be repeated.
+    #[arg(long = "state", value_name = "CODE")]
+    pub states: Vec<String>,
+
+    /// when the CSVs were downloaded from USDA. defaults to when the newest of them was last modified.
+    #[arg(long, value_name = "DATE")]
+    pub downloaded: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Args)]
+pub struct ExportOptions {
+    /// run the export without writing anything and print what would be added to the gallformers database
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// write a CSV report of the place, placeplace and speciesplace rows that were, or would be, added
+    #[arg(long, value_name = "CSV")]
+    pub report: Option<PathBuf>,
+
+    /// whether a host that is a species also gets the ranges of its infraspecific taxa
+    #[arg(long, value_enum, default_value_t)]
+    pub rollup: Rollup,
+
+    /// also remove the range links that an earlier export of the source asserted but that its data no longer supports.
+    /// links that were entered by hand, or that another source asserts, are kept.
+    #[arg(long)]
+    pub sync: bool,
+
+    /// the VASCAN occurrence statuses that put a host in a place's range, e.g., --occurrence native,doubtful. only for
+    /// export vascan, the places with any other status are skipped. defaults to present, native, introduced and
+    /// ephemeral.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "STATUS")]
+    pub occurrence: Vec<Occurrence>,
+}
+
+/// how the ranges of infraspecific taxa are used for hosts. a host that is itself infraspecific, e.g.,
+/// `Acer saccharum var. nigrum`, only ever gets the range of that taxon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Rollup {
+    /// a species gets its own range along with the ranges of all of its subspecies, varieties and formas
+    #[default]
+    Species,
+    /// a species only gets its own range, the infraspecific taxa are kept separate
+    Separate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Source {
+    Usda,
+    Vascan,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths() {
+        let cli = Cli::try_parse_from(["importer", "import", "usda"]).unwrap();
+        assert_eq!(cli.paths.plants_db, PathBuf::from("plants.db"));
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                source: Source::Usda,
+                options: ImportOptions { strict: false, .. }
+            }
+        ));
+
+        let cli = Cli::try_parse_from([
+            "importer",
+            "export",
+            "vascan",
+            "--gf-db",
+            "/tmp/staging.sqlite",
+            "--plants-db",
+            "/tmp/plants.db",
+        ])
+        .unwrap();
+        assert_eq!(cli.paths.gf_db, PathBuf::from("/tmp/staging.sqlite"));
+        assert_eq!(cli.paths.plants_db, PathBuf::from("/tmp/plants.db"));
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                source: Source::Vascan,
+                options: ExportOptions { dry_run: false, .. }
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["importer", "export", "usda", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                source: Source::Usda,
+                options: ExportOptions {
+                    dry_run: true,
+                    rollup: Rollup::Species,
+                    ..
+                }
+            }
+        ));
+
+        let cli =
+            Cli::try_parse_from(["importer", "export", "usda", "--rollup", "separate"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                options: ExportOptions {
+                    rollup: Rollup::Separate,
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["importer", "export", "usda", "--rollup", "foo"]).is_err());
+
+        let cli =
+            Cli::try_parse_from(["importer", "export", "usda", "--sync", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                options: ExportOptions {
+                    sync: true,
+                    dry_run: true,
+                    ..
+                },
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["importer", "export", "vascan"]).unwrap();
+        match cli.command {
+            Command::Export { options, .. } => assert!(options.occurrence.is_empty()),
+            c => panic!("{:?}", c),
+        }
+        let cli = Cli::try_parse_from([
+            "importer",
+            "export",
+            "vascan",
+            "--occurrence",
+            "native,doubtful",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Export { options, .. } => assert_eq!(
+                options.occurrence,
+                vec![Occurrence::Native, Occurrence::Doubtful]
+            ),
+            c => panic!("{:?}", c),
+        }
+        assert!(
+            Cli::try_parse_from(["importer", "export", "vascan", "--occurrence", "absent"])
+                .is_err()
+        );
+        // the statuses are only read when exporting
+        assert!(
+            Cli::try_parse_from(["importer", "import", "vascan", "--occurrence", "native"])
+                .is_err()
+        );
+
+        let cli = Cli::try_parse_from(["importer", "prune"]).unwrap();
+        assert!(matches!(cli.command, Command::Prune { keep: 2 }));
+        assert!(Cli::try_parse_from(["importer", "prune", "--keep", "0"]).is_err());
+
+        assert!(Cli::try_parse_from(["importer"]).is_err());
+        assert!(Cli::try_parse_from(["importer", "import", "foo"]).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// where in the input an error happened. not every error can be tied to a file or a row, so all of the parts are
+/// optional.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+    pub file: Option<PathBuf>,
+    pub row: Option<u64>,
+    pub raw: Option<String>,
+}
+
+impl Location {
+    pub fn file(file: &Path) -> Self {
+        Location {
+            file: Some(file.to_path_buf()),
+            ..Default::default()
+        }
+    }
+
+    pub fn row(file: &Path, row: u64, raw: &str) -> Self {
+        Location {
+            file: Some(file.to_path_buf()),
+            row: Some(row),
+            raw: Some(raw.to_string()),
+        }
+    }
+}
+
+/// renders as a `file:row: ` prefix for error messages, or nothing if there is no file.
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.row) {
+            (Some(file), Some(row)) => write!(f, "{}:{}: ", file.display(), row),
+            (Some(file), None) => write!(f, "{}: ", file.display()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ImporterError {
+    #[error("{at}could not decode the CSV record: {source}")]
+    Csv {
+        at: Location,
+        #[source]
+        source: csv::Error,
+    },
+    #[error("{at}expected {expected} fields but the record has {found}")]
+    FieldCount {
+        at: Location,
+        expected: usize,
+        found: usize,
+    },
+    #[error("{at}could not parse the plant name {name:?}")]
+    NameParse { at: Location, name: String },
+    #[error("{at}unknown region {code:?}")]
+    UnknownRegion { at: Location, code: String },
+    #[error("{at}the data for {regions} looks incomplete")]
+    Incomplete { at: Location, regions: String },
+    #[error("{at}database error: {source}")]
+    Db {
+        at: Location,
+        // boxed as the sqlite error is large enough to bloat every result in the importer otherwise
+        #[source]
+        source: Box<rusqlite::Error>,
+    },
+    #[error("{at}HTTP request failed: {source}")]
+    Http {
+        at: Location,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{at}invalid JSON: {source}")]
+    Json {
+        at: Location,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("{at}{source}")]
+    Io {
+        at: Location,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{at}{message}")]
+    Other { at: Location, message: String },
+}
+
+impl ImporterError {
+    pub fn location(&self) -> &Location {
+        match self {
+            ImporterError::Csv { at, .. }
+            | ImporterError::FieldCount { at, .. }
+            | ImporterError::NameParse { at, .. }
+            | ImporterError::UnknownRegion { at, .. }
+            | ImporterError::Incomplete { at, .. }
+            | ImporterError::Db { at, .. }
+            | ImporterError::Http { at, .. }
+            | ImporterError::Json { at, .. }
+            | ImporterError::Io { at, .. }
+            | ImporterError::Other { at, .. } => at,
+        }
+    }
+
+    fn location_mut(&mut self) -> &mut Location {
+        match self {
+            ImporterError::Csv { at, .. }
+            | ImporterError::FieldCount { at, .. }
+            | ImporterError::NameParse { at, .. }
+            | ImporterError::UnknownRegion { at, .. }
+            | ImporterError::Incomplete { at, .. }
+            | ImporterError::Db { at, .. }
+            | ImporterError::Http { at, .. }
+            | ImporterError::Json { at, .. }
+            | ImporterError::Io { at, .. }
+            | ImporterError::Other { at, .. } => at,
+        }
+    }
+
+    /// sets where the error happened, unless the error already knows.
+    pub fn at(mut self, loc: Location) -> Self {
+        let at = self.location_mut();
+        if at.file.is_none() {
+            *at = loc;
+        }
+        self
+    }
+
+    fn is_constraint_violation(&self) -> bool {
+        match self {
+            ImporterError::Db { source, .. } => {
+                source.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation)
+            }
+            _ => false,
+        }
+    }
+
+    /// the process exit code for the error. these are stable so that scripts can branch on them. 2 is not used as it
+    /// is what the argument parser exits with for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ImporterError::Other { .. } | ImporterError::Io { .. } => 1,
+            ImporterError::Csv { .. } | ImporterError::FieldCount { .. } => 3,
+            ImporterError::NameParse { .. } => 4,
+            ImporterError::UnknownRegion { .. } => 5,
+            ImporterError::Db { .. } if self.is_constraint_violation() => 6,
+            ImporterError::Db { .. } => 7,
+            ImporterError::Http { .. } | ImporterError::Json { .. } => 8,
+            ImporterError::Incomplete { .. } => 9,
+        }
+    }
+
+    /// a suggestion for how to fix the problem, if there is one.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ImporterError::Csv { .. } | ImporterError::FieldCount { .. } => Some(
+                "the record is malformed. fix the row in the CSV file (or re-download it) and run the import again, \
+                 or run the import without --strict to quarantine it",
+            ),
+            ImporterError::NameParse { .. } => {
+                Some("the name is not in a format the parser understands. check the row in the CSV file")
+            }
+            ImporterError::UnknownRegion { .. } => {
+                Some("add the region code and name to regions.json or remove the CSV file from the data directory")
+            }
+            ImporterError::Incomplete { .. } => Some(
+                "re-download the CSVs for the regions, or run the import without --strict to import them anyway",
+            ),
+            ImporterError::Db { .. } if self.is_constraint_violation() => Some(
+                "the record conflicts with data already in the database. nothing was written, check the record",
+            ),
+            ImporterError::Db { .. } => Some("check that the database paths are correct and the schema is up to date"),
+            ImporterError::Http { .. } | ImporterError::Json { .. } => {
+                Some("the remote service failed or returned something unexpected. try again later")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ImporterError {
+    fn from(source: rusqlite::Error) -> Self {
+        ImporterError::Db {
+            at: Default::default(),
+            source: Box::new(source),
+        }
+    }
+}
+
+impl From<csv::Error> for ImporterError {
+    fn from(source: csv::Error) -> Self {
+        ImporterError::Csv {
+            at: Default::default(),
+            source,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ImporterError {
+    fn from(source: reqwest::Error) -> Self {
+        ImporterError::Http {
+            at: Default::default(),
+            source,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ImporterError {
+    fn from(source: serde_json::Error) -> Self {
+        ImporterError::Json {
+            at: Default::default(),
+            source,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImporterError {
+    fn from(source: std::io::Error) -> Self {
+        ImporterError::Io {
+            at: Default::default(),
+            source,
+        }
+    }
+}
+
+impl From<String> for ImporterError {
+    fn from(message: String) -> Self {
+        ImporterError::Other {
+            at: Default::default(),
+            message,
+        }
+    }
+}
+
+impl From<&str> for ImporterError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// adds a location to any error that can be turned into an ImporterError
+pub trait ResultExt<T> {
+    fn at(self, loc: &Location) -> Result<T, ImporterError>;
+}
+
+impl<T, E: Into<ImporterError>> ResultExt<T> for Result<T, E> {
+    fn at(self, loc: &Location) -> Result<T, ImporterError> {
+        self.map_err(|e| e.into().at(loc.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_messages() {
+        let e = ImporterError::NameParse {
+            at: Location::row(Path::new("data/ID.csv"), 12, "\"ABC\",\"\",\"Foo\""),
+            name: "Foo".to_string(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "data/ID.csv:12: could not parse the plant name \"Foo\""
+        );
+        assert_eq!(e.exit_code(), 4);
+
+        let e: ImporterError = "Unknown Region".into();
+        let e = e.at(Location::file(Path::new("data/XX.csv")));
+        assert_eq!(e.to_string(), "data/XX.csv: Unknown Region");
+        assert_eq!(e.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_constraint_exit_code() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (name TEXT NOT NULL UNIQUE); INSERT INTO t VALUES ('a');",
+        )
+        .unwrap();
+        let e: ImporterError = conn
+            .execute("INSERT INTO t VALUES ('a')", [])
+            .unwrap_err()
+            .into();
+        assert_eq!(e.exit_code(), 6);
+
+        let e: ImporterError = conn
+            .execute("INSERT INTO nope VALUES ('a')", [])
+            .unwrap_err()
+            .into();
+        assert_eq!(e.exit_code(), 7);
+    }
+}
@@ -1,72 +1,278 @@
-use crate::gallformersdb::GallformersDB;
-use crate::plantdb::PlantDB;
-use crate::species::Species;
-use crate::species::SpeciesName;
+use crate::backup::backup;
+use crate::cli::{ExportOptions, Paths, Rollup};
+use crate::gallformersdb::{GallformersDB, PlaceAssertion, PlaceSource};
+use crate::plant::{plant_name_to_species_name, NameStyle, PlantName};
+use crate::plantdb::{AliasType, PlantDB};
+use crate::reconcile::{mapped_name, read_name_map};
+use crate::report::{Change, ExportReport};
+use crate::species::{Species, SpeciesName};
 use crate::Res;
 use rusqlite::Connection;
-use std::collections::HashMap;
-use std::path::PathBuf;
-
-pub async fn export() -> Res<()> {
-    let mut gf_db_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    gf_db_file.pop();
-    gf_db_file.push("prisma/gallformers.sqlite");
-    let gf_c = Connection::open(gf_db_file.as_path())?;
-    let mut gf_db = GallformersDB::new(&gf_c);
-
-    let mut plant_db_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    plant_db_file.push("plants.db");
-    let plant_c = Connection::open(plant_db_file.as_path())?;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// the taxon in plants.db that a host's range comes from
+pub enum Resolved {
+    /// the host's own name, which is not a synonym
+    Accepted(SpeciesName),
+    /// the accepted taxon that the host's name is a synonym of, along with its full name
+    Synonym(SpeciesName, PlantName),
+    /// the accepted taxa that the host's name is a synonym of, when there is more than one
+    Ambiguous(Vec<PlantName>),
+}
+
+/// resolves a host's name to the taxon that its range comes from. a host that uses a synonym gets the range of the
+/// accepted taxon.
+pub fn resolve_host(plant_db: &mut PlantDB, name: SpeciesName) -> Res<Resolved> {
+    let mut accepted = plant_db.select_accepted_names(&name)?;
+    Ok(match accepted.len() {
+        0 => Resolved::Accepted(name),
+        1 => {
+            let a = accepted.remove(0);
+            Resolved::Synonym(plant_name_to_species_name(&a), a)
+        }
+        _ => Resolved::Ambiguous(accepted),
+    })
+}
+
+pub async fn export(paths: &Paths, options: &ExportOptions) -> Res<()> {
+    // USDA does not give an occurrence status
+    if !options.occurrence.is_empty() {
+        return Err("--occurrence is only supported by export vascan".into());
+    }
+    let mut gf_c = Connection::open(&paths.gf_db)?;
+    if !options.dry_run {
+        backup(&gf_c, &paths.gf_db, &paths.backup_dir)?;
+    }
+
+    let plant_c = Connection::open(&paths.plants_db)?;
     let mut plant_db = PlantDB::new(&plant_c);
 
-    let mut stmt = gf_db
-        .conn
-        .prepare("SELECT id, name from species WHERE taxoncode = 'plant';")?;
-    let species = stmt.query_map([], |row| {
-        Ok(Species {
-            id: row.get(0)?,
-            name: row.get(1)?,
-        })
-    })?;
-
-    let mut species_map = HashMap::new();
-    for sp in species {
-        let sp_name = SpeciesName::new(sp.as_ref().unwrap().name.to_string());
-        species_map.insert(sp_name, sp.unwrap().id);
+    // everything is written inside of a single transaction. if anything fails the transaction is rolled back when it
+    // is dropped, and for a dry run it is rolled back explicitly so that the report reflects exactly what would happen.
+    let tx = gf_c.transaction()?;
+    let name_map = read_name_map(&paths.name_map)?;
+    let report = export_places(
+        &mut GallformersDB::new(&tx),
+        &mut plant_db,
+        options.rollup,
+        options.sync,
+        &name_map,
+    )?;
+
+    if options.dry_run {
+        tx.rollback()?;
+        report.print();
+        println!("Dry run, nothing was written to {:?}.", paths.gf_db);
+    } else {
+        tx.commit()?;
+        report.print_summary();
     }
 
+    if let Some(csv) = &options.report {
+        report.write_csv(csv)?;
+        println!("Wrote export report to {:?}.", csv);
+    }
+
+    Ok(())
+}
+
+fn export_places(
+    gf_db: &mut GallformersDB,
+    plant_db: &mut PlantDB,
+    rollup: Rollup,
+    sync: bool,
+    name_map: &BTreeMap<String, String>,
+) -> Res<ExportReport> {
     // this code is slow and could be sped up by at least an order of magnitude if we were to reverse the loops and only
     // traverse the large USDA plants set once. However the APIs we need for the more general lookup do not work that way
     // so we will just live with slow code. It is run only rarely anyhow.
-    gf_db.conn.execute_batch("BEGIN TRANSACTION;")?;
+    let mut report = ExportReport::new();
+
+    // a list rather than a map as more than one host can be mapped to the same name
+    let mut species_map = Vec::new();
+    for (name, sp) in gf_db.select_all_plants()? {
+        // hosts in the name map are looked up by the name they were reconciled with
+        match mapped_name(&name, name_map) {
+            Ok(n) => {
+                species_map.push((n, sp));
+            }
+            Err(e) => {
+                println!("Failed to parse the host name: {}", e);
+                report.unmatched(&sp.name);
+            }
+        }
+    }
 
     // add all of the regions in the plants db as places and since they are all states assign them to the US
     let country_us = gf_db.select_place_by_name("United States")?.unwrap();
     let mut places = HashMap::new();
     for r in plant_db.select_all_regions()? {
-        let id = gf_db.create_or_fetch_place(&r)?;
-        gf_db.create_place_place(country_us.id, id)?;
+        let (id, created) = gf_db.create_or_fetch_place(&r)?;
+        report.place(&r.name, Change::from_inserted(created));
+        let linked = gf_db.create_place_place(country_us.id, id)?;
+        report.place_parent(&r.name, &country_us.name, Change::from_inserted(linked));
         places.insert(r.name.clone(), id);
     }
 
-    for s in species_map {
-        let species_id = s.1;
-        match plant_db.select_plant_regions(s.0.clone()) {
+    // every range link is recorded as coming from the latest import of the USDA checklist
+    let (version, asof) = match plant_db.select_snapshots()?.pop() {
+        Some(s) => (s.id.to_string(), s.downloaded),
+        None => Default::default(),
+    };
+
+    // every range link that the USDA data supports, so that a sync can remove the rest
+    let mut asserted = HashSet::new();
+
+    for (name, sp) in species_map {
+        // the host is put in the genus it is named in, even if its accepted taxon is in another
+        let genus = name.genus.clone();
+        let name = match resolve_host(plant_db, name)? {
+            Resolved::Accepted(name) => name,
+            Resolved::Synonym(name, a) => {
+                report.synonym(&sp.name, &a.render(NameStyle::Gallformers));
+                name
+            }
+            Resolved::Ambiguous(accepted) => {
+                let names = accepted
+                    .iter()
+                    .map(|a| a.render(NameStyle::Gallformers))
+                    .collect();
+                report.ambiguous(&sp.name, names);
+                continue;
+            }
+        };
+        match plant_db.select_plant_regions(name.clone(), rollup) {
             Ok(regions) => {
                 if regions.is_empty() {
-                    println!("No match for: {:?}", s.0);
+                    report.unmatched(&sp.name);
+                } else {
+                    // the old names of the host so that searching for them on the site finds it
+                    for synonym in plant_db.select_synonym_names(&name)? {
+                        if synonym != sp.name {
+                            let added = gf_db.add_alias_for_species(
+                                sp.id,
+                                &synonym,
+                                AliasType::Scientific,
+                            )?;
+                            report.alias(
+                                &sp.name,
+                                &synonym,
+                                &AliasType::Scientific.to_string(),
+                                Change::from_inserted(added),
+                            );
+                        }
+                    }
+                    if let Some(family) = plant_db.select_family(&name)? {
+                        export_taxonomy(gf_db, &mut report, &sp, &genus, &family)?;
+                    }
+                    for common in plant_db.select_common_names(&name)? {
+                        let added =
+                            gf_db.add_alias_for_species(sp.id, &common, AliasType::Common)?;
+                        report.alias(
+                            &sp.name,
+                            &common,
+                            &AliasType::Common.to_string(),
+                            Change::from_inserted(added),
+                        );
+                    }
                 }
-                for region in regions {
+                for (region, r) in regions {
                     match places.get(&region.name) {
-                        Some(place_id) => gf_db.add_place_for_plant(species_id, *place_id)?,
+                        Some(place_id) => {
+                            asserted.insert((sp.id, *place_id));
+                            let added = gf_db.assert_place_for_plant(
+                                sp.id,
+                                *place_id,
+                                &PlaceAssertion {
+                                    source: PlaceSource::Usda,
+                                    version: &version,
+                                    asof: &asof,
+                                    record: &r.records.join("; "),
+                                    nativity: None,
+                                    occurrence: None,
+                                },
+                            )?;
+                            report.species_place(
+                                &sp.name,
+                                &region.name,
+                                Change::from_inserted(added),
+                                r.matched,
+                            );
+                        }
                         None => println!("Failed to lookup place id for given region {:?}", region),
                     }
                 }
             }
-            _ => println!("No match for: {:?}", s.0),
+            _ => report.unmatched(&sp.name),
         };
     }
-    gf_db.conn.execute_batch("END TRANSACTION;")?;
 
+    if sync {
+        remove_stale_places(gf_db, &mut report, PlaceSource::Usda, &asserted)?;
+    }
+
+    Ok(report)
+}
+
+/// removes the source from every range link that an earlier export of it asserted but that this one did not, e.g.,
+/// because the source dropped the state from the taxon or the host was matched to the wrong taxon. the link itself is
+/// only removed if no other source asserts it. links without any source were entered by hand and are never touched.
+pub fn remove_stale_places(
+    gf_db: &mut GallformersDB,
+    report: &mut ExportReport,
+    source: PlaceSource,
+    asserted: &HashSet<(i64, i64)>,
+) -> Res<()> {
+    for (species_id, species, place_id, place) in gf_db.select_sourced_places(source)? {
+        if !asserted.contains(&(species_id, place_id)) {
+            let remaining = gf_db.remove_place_source(species_id, place_id, source)?;
+            report.species_place_removed(
+                &species,
+                &place,
+                &source.to_string(),
+                remaining.iter().map(|s| s.to_string()).collect(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// puts the host in its genus, adding the genus and its family if gallformers does not have them. a genus that
+/// gallformers has in a different family is reported as a conflict and is left alone, as are hosts that are already in
+/// a genus.
+fn export_taxonomy(
+    gf_db: &mut GallformersDB,
+    report: &mut ExportReport,
+    sp: &Species,
+    genus: &str,
+    family: &str,
+) -> Res<()> {
+    let genera = gf_db.select_genera_by_name(genus)?;
+    let genus_id = match genera.iter().find(|(_, f)| f.as_deref() == Some(family)) {
+        Some((id, _)) => {
+            report.genus(genus, family, Change::Exists);
+            *id
+        }
+        None => match genera.iter().find(|(_, f)| f.is_some()) {
+            Some((_, gf_family)) => {
+                report.family_conflict(genus, gf_family.clone(), family);
+                return Ok(());
+            }
+            None => {
+                // either a new genus or one that was added without a family
+                let (family_id, created) = gf_db.create_or_fetch_family(family)?;
+                report.family(family, Change::from_inserted(created));
+                let id = gf_db.add_genus_to_family(
+                    genera.first().map(|(id, _)| *id),
+                    genus,
+                    family_id,
+                )?;
+                report.genus(genus, family, Change::Add);
+                id
+            }
+        },
+    };
+    let added = gf_db.add_genus_for_species(sp.id, genus_id)?;
+    report.species_genus(&sp.name, genus, Change::from_inserted(added));
     Ok(())
 }
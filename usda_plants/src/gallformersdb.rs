@@ -1,8 +1,50 @@
+use crate::nativity::{Nativity, Occurrence};
+use crate::plantdb::AliasType;
 use crate::species::Species;
-use crate::util::Region;
-use rusqlite::{Connection, Error, Statement};
+use crate::util::{timestamp, Region};
+use rusqlite::{named_params, params, Connection, Error, Statement};
 use std::collections::HashMap;
-use std::convert::TryInto;
+use strum_macros::{Display, EnumString};
+
+/// who asserted that a host is in a place
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum PlaceSource {
+    Usda,
+    Vascan,
+    /// entered by a curator
+    Manual,
+}
+
+/// what a source says about a host being in a place
+#[derive(Debug)]
+pub struct PlaceAssertion<'s> {
+    pub source: PlaceSource,
+    /// the USDA snapshot id or the VASCAN API version
+    pub version: &'s str,
+    /// when the source data is from
+    pub asof: &'s str,
+    /// the raw data from the source that put the host in the place
+    pub record: &'s str,
+    pub nativity: Option<Nativity>,
+    /// VASCAN's occurrence status, USDA does not give one
+    pub occurrence: Option<Occurrence>,
+}
+
+/// a speciesplace row along with one of the sources that asserted it. a row without a recorded source has None.
+#[derive(Debug, PartialEq)]
+pub struct SpeciesPlaceSource {
+    pub place: String,
+    pub source: Option<PlaceSource>,
+    pub version: String,
+    pub asof: String,
+    pub recorded: String,
+    pub record: String,
+    /// what the source said about whether the host is native to the place
+    pub nativity: Option<Nativity>,
+    /// what the source said about the host occurring in the place, e.g., doubtful
+    pub occurrence: Option<Occurrence>,
+}
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 pub struct PlantSpecies {
@@ -20,6 +62,11 @@ pub struct GallformersDB<'a> {
     create_place_place_statement: Option<Statement<'a>>,
     select_all_plants_statement: Option<Statement<'a>>,
     select_places_by_type_statement: Option<Statement<'a>>,
+    alias_exists_statement: Option<Statement<'a>>,
+    select_genera_by_name_statement: Option<Statement<'a>>,
+    add_genus_for_species_statement: Option<Statement<'a>>,
+    record_place_source_statement: Option<Statement<'a>>,
+    has_place_source_statement: Option<Statement<'a>>,
 }
 
 impl<'a> GallformersDB<'a> {
@@ -33,6 +80,11 @@ impl<'a> GallformersDB<'a> {
             create_place_place_statement: None,
             select_all_plants_statement: None,
             select_places_by_type_statement: None,
+            alias_exists_statement: None,
+            select_genera_by_name_statement: None,
+            add_genus_for_species_statement: None,
+            record_place_source_statement: None,
+            has_place_source_statement: None,
         }
     }
 
@@ -77,7 +129,8 @@ impl<'a> GallformersDB<'a> {
         rows.next().transpose()
     }
 
-    pub fn create_or_fetch_place(&mut self, region: &Region) -> Result<i64, Error> {
+    /// inserts a place, if it does not already exist. returns the id of the place and whether it was newly created.
+    pub fn create_or_fetch_place(&mut self, region: &Region) -> Result<(i64, bool), Error> {
         if self.create_place_statement.is_none() {
             let stmt = self.conn.prepare(
                 "INSERT OR IGNORE INTO place (name, code, type) VALUES (:name, :code, :type);",
@@ -90,37 +143,331 @@ impl<'a> GallformersDB<'a> {
             (":type", &region.typ),
         ])?;
         match r {
-            0 => Ok(self.select_place_by_name(&region.name)?.unwrap().id),
-            id => Ok(id.try_into().unwrap()),
+            0 => Ok((self.select_place_by_name(&region.name)?.unwrap().id, false)),
+            _ => Ok((self.conn.last_insert_rowid(), true)),
         }
     }
 
-    pub fn add_place_for_plant(&mut self, species_id: i64, place_id: i64) -> Result<(), Error> {
+    /// relates a place to a plant, if they are not already related. returns whether a new relationship was created.
+    pub fn add_place_for_plant(&mut self, species_id: i64, place_id: i64) -> Result<bool, Error> {
         if self.add_place_for_plant_statement.is_none() {
             let stmt = self.conn.prepare(
                 "INSERT OR IGNORE INTO speciesplace (species_id, place_id) VALUES (:species_id, :place_id);",
             )?;
             self.add_place_for_plant_statement = Some(stmt);
         }
-        self.add_place_for_plant_statement
+        let r = self
+            .add_place_for_plant_statement
             .as_mut()
             .unwrap()
             .execute(&[(":species_id", &species_id), (":place_id", &place_id)])?;
+        Ok(r > 0)
+    }
+
+    /// adds a range link on behalf of an importer and records the importer as a source of it. a link that was already
+    /// there without any recorded source was entered by a curator on the site, and is left alone rather than being
+    /// claimed by the importer. one with a `manual` source gets the importer as a source beside it, which a sync can
+    /// not remove the link for. returns true if the link was added.
+    pub fn assert_place_for_plant(
+        &mut self,
+        species_id: i64,
+        place_id: i64,
+        assertion: &PlaceAssertion,
+    ) -> Result<bool, Error> {
+        let added = self.add_place_for_plant(species_id, place_id)?;
+        if added || self.has_place_source(species_id, place_id)? {
+            self.record_place_source(species_id, place_id, assertion)?;
+        }
+        Ok(added)
+    }
+
+    /// true if any source, including a curator, is recorded for the speciesplace row
+    fn has_place_source(&mut self, species_id: i64, place_id: i64) -> Result<bool, Error> {
+        if self.has_place_source_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT EXISTS (
+                    SELECT 1 FROM speciesplacesource 
+                    WHERE species_id = ?1 AND place_id = ?2
+                );",
+            )?;
+            self.has_place_source_statement = Some(stmt);
+        }
+        self.has_place_source_statement
+            .as_mut()
+            .unwrap()
+            .query_row(params![species_id, place_id], |r| r.get(0))
+    }
+
+    /// records where a speciesplace row came from, replacing what was recorded the last time the same source asserted
+    /// it. if the source says whether the host is native there the row's nativity is set to match, unless a curator
+    /// has marked the host as invasive. the row's occurrence status is set the same way, so that e.g. doubtful records
+    /// can be shown as such.
+    pub fn record_place_source(
+        &mut self,
+        species_id: i64,
+        place_id: i64,
+        assertion: &PlaceAssertion,
+    ) -> Result<(), Error> {
+        if self.record_place_source_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "INSERT OR REPLACE INTO speciesplacesource (species_id, place_id, source, version, asof, recorded, record, nativity, occurrence) 
+                    VALUES (:species_id, :place_id, :source, :version, :asof, :recorded, :record, :nativity, :occurrence);",
+            )?;
+            self.record_place_source_statement = Some(stmt);
+        }
+        let nativity = assertion.nativity.map(|n| n.to_string());
+        let occurrence = assertion.occurrence.map(|o| o.to_string());
+        self.record_place_source_statement
+            .as_mut()
+            .unwrap()
+            .execute(named_params! {
+                ":species_id": species_id,
+                ":place_id": place_id,
+                ":source": assertion.source.to_string(),
+                ":version": assertion.version,
+                ":asof": assertion.asof,
+                ":recorded": timestamp(),
+                ":record": assertion.record,
+                ":nativity": nativity,
+                ":occurrence": occurrence,
+            })?;
+        if nativity.is_some() {
+            self.conn.execute(
+                "UPDATE speciesplace SET nativity = ?1 
+                    WHERE species_id = ?2 AND place_id = ?3 AND nativity IS NOT 'invasive';",
+                params![nativity, species_id, place_id],
+            )?;
+        }
+        if occurrence.is_some() {
+            self.conn.execute(
+                "UPDATE speciesplace SET occurrence = ?1 WHERE species_id = ?2 AND place_id = ?3;",
+                params![occurrence, species_id, place_id],
+            )?;
+        }
         Ok(())
     }
 
-    pub fn create_place_place(&mut self, parent_id: i64, place_id: i64) -> Result<(), Error> {
+    /// fetches every speciesplace row that the source asserted as (species id, species name, place id, place name)
+    pub fn select_sourced_places(
+        &mut self,
+        source: PlaceSource,
+    ) -> Result<Vec<(i64, String, i64, String)>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT sp.id, sp.name, p.id, p.name 
+                FROM speciesplacesource AS s 
+                    INNER JOIN species AS sp ON (sp.id = s.species_id) 
+                    INNER JOIN place AS p ON (p.id = s.place_id) 
+                WHERE s.source = :source 
+                ORDER BY sp.name, p.name;",
+        )?;
+        let rows = stmt.query_map(&[(":source", &source.to_string())], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+        })?;
+        rows.collect()
+    }
+
+    /// removes a source from a speciesplace row, and the row itself if no other source asserts it. a row that a curator
+    /// asserts always has a `manual` source, or no source at all, so it is never removed. returns the sources that
+    /// still assert it.
+    pub fn remove_place_source(
+        &mut self,
+        species_id: i64,
+        place_id: i64,
+        source: PlaceSource,
+    ) -> Result<Vec<PlaceSource>, Error> {
+        self.conn.execute(
+            "DELETE FROM speciesplacesource WHERE species_id = ?1 AND place_id = ?2 AND source = ?3;",
+            params![species_id, place_id, source.to_string()],
+        )?;
+        let mut stmt = self.conn.prepare(
+            "SELECT source FROM speciesplacesource WHERE species_id = ?1 AND place_id = ?2 ORDER BY source;",
+        )?;
+        let remaining = stmt
+            .query_map(params![species_id, place_id], |r| r.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .filter_map(|s| s.parse().ok())
+            .collect::<Vec<_>>();
+        if remaining.is_empty() {
+            self.conn.execute(
+                "DELETE FROM speciesplace WHERE species_id = ?1 AND place_id = ?2;",
+                params![species_id, place_id],
+            )?;
+        }
+        Ok(remaining)
+    }
+
+    /// fetches all of the places a host is in along with every source recorded for each of them, by place
+    pub fn select_place_sources(&mut self, host: &str) -> Result<Vec<SpeciesPlaceSource>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.name, s.source, s.version, s.asof, s.recorded, s.record, s.nativity, s.occurrence 
+                FROM species AS sp 
+                    INNER JOIN speciesplace AS spp ON (spp.species_id = sp.id) 
+                    INNER JOIN place AS p ON (p.id = spp.place_id) 
+                    LEFT JOIN speciesplacesource AS s ON (s.species_id = spp.species_id AND s.place_id = spp.place_id) 
+                WHERE sp.name = :name AND sp.taxoncode = 'plant' 
+                ORDER BY p.name, s.source;",
+        )?;
+        let rows = stmt.query_map(&[(":name", &host)], |r| {
+            let source: Option<String> = r.get(1)?;
+            Ok(SpeciesPlaceSource {
+                place: r.get(0)?,
+                source: source.and_then(|s| s.parse().ok()),
+                version: r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                asof: r.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                recorded: r.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                record: r.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                nativity: r.get::<_, Option<String>>(6)?.and_then(|n| n.parse().ok()),
+                occurrence: r.get::<_, Option<String>>(7)?.and_then(|o| o.parse().ok()),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// adds an alias to a species, unless the species already has an alias with the name, of any type. each species
+    /// gets its own alias row as the aliases are edited per species on the site. returns whether the alias was added.
+    pub fn add_alias_for_species(
+        &mut self,
+        species_id: i64,
+        name: &str,
+        alias_type: AliasType,
+    ) -> Result<bool, Error> {
+        if self.alias_exists_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT a.id FROM alias AS a 
+                    INNER JOIN aliasspecies AS s ON (s.alias_id = a.id) 
+                    WHERE s.species_id = :species_id AND a.name = :name COLLATE NOCASE;",
+            )?;
+            self.alias_exists_statement = Some(stmt);
+        }
+        let exists = self
+            .alias_exists_statement
+            .as_mut()
+            .unwrap()
+            .exists(named_params! {":species_id": species_id, ":name": name})?;
+        if exists {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "INSERT INTO alias (name, type) VALUES (?1, ?2);",
+            params![name, alias_type.to_string()],
+        )?;
+        self.conn.execute(
+            "INSERT INTO aliasspecies (species_id, alias_id) VALUES (?1, ?2);",
+            params![species_id, self.conn.last_insert_rowid()],
+        )?;
+        Ok(true)
+    }
+
+    /// fetches the genera with the name, oldest first, along with the name of the family that each is in. a genus that
+    /// is not in a family has None.
+    pub fn select_genera_by_name(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<(i64, Option<String>)>, Error> {
+        if self.select_genera_by_name_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT g.id, f.name FROM taxonomy AS g 
+                    LEFT JOIN taxonomy AS f ON (f.id = g.parent_id AND f.type = 'family') 
+                    WHERE g.type = 'genus' AND g.name = :name 
+                    ORDER BY g.id;",
+            )?;
+            self.select_genera_by_name_statement = Some(stmt);
+        }
+        let rows = self
+            .select_genera_by_name_statement
+            .as_mut()
+            .unwrap()
+            .query_map(&[(":name", &name)], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// inserts a family, if there is not one with the name already. returns the id of the family and whether it was
+    /// newly created.
+    pub fn create_or_fetch_family(&mut self, name: &str) -> Result<(i64, bool), Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM taxonomy WHERE type = 'family' AND name = :name ORDER BY id;",
+        )?;
+        let mut rows = stmt.query_map(&[(":name", &name)], |r| r.get(0))?;
+        if let Some(id) = rows.next().transpose()? {
+            return Ok((id, false));
+        }
+        self.conn.execute(
+            "INSERT INTO taxonomy (name, type) VALUES (?1, 'family');",
+            [name],
+        )?;
+        Ok((self.conn.last_insert_rowid(), true))
+    }
+
+    /// puts a genus in a family, both as its parent and in taxonomytaxonomy as the admin screens do. a genus without an
+    /// id is created first. returns the id of the genus.
+    pub fn add_genus_to_family(
+        &mut self,
+        genus_id: Option<i64>,
+        name: &str,
+        family_id: i64,
+    ) -> Result<i64, Error> {
+        let genus_id = match genus_id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE taxonomy SET parent_id = ?1 WHERE id = ?2;",
+                    params![family_id, id],
+                )?;
+                id
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO taxonomy (name, type, parent_id) VALUES (?1, 'genus', ?2);",
+                    params![name, family_id],
+                )?;
+                self.conn.last_insert_rowid()
+            }
+        };
+        self.conn.execute(
+            "INSERT OR IGNORE INTO taxonomytaxonomy (taxonomy_id, child_id) VALUES (?1, ?2);",
+            params![family_id, genus_id],
+        )?;
+        Ok(genus_id)
+    }
+
+    /// relates a species to a genus, unless the species is already in a genus. returns whether a new relationship was
+    /// created.
+    pub fn add_genus_for_species(&mut self, species_id: i64, genus_id: i64) -> Result<bool, Error> {
+        if self.add_genus_for_species_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "INSERT INTO speciestaxonomy (species_id, taxonomy_id) 
+                    SELECT :species_id, :genus_id 
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM speciestaxonomy AS st 
+                            INNER JOIN taxonomy AS t ON (t.id = st.taxonomy_id) 
+                            WHERE st.species_id = :species_id AND t.type = 'genus'
+                    );",
+            )?;
+            self.add_genus_for_species_statement = Some(stmt);
+        }
+        let r = self
+            .add_genus_for_species_statement
+            .as_mut()
+            .unwrap()
+            .execute(named_params! {":species_id": species_id, ":genus_id": genus_id})?;
+        Ok(r > 0)
+    }
+
+    /// relates a place to its parent place, if they are not already related. returns whether a new relationship was
+    /// created.
+    pub fn create_place_place(&mut self, parent_id: i64, place_id: i64) -> Result<bool, Error> {
         if self.create_place_place_statement.is_none() {
             let stmt = self.conn.prepare(
                 "INSERT OR IGNORE INTO placeplace (parent_id, place_id) VALUES (:parent_id, :place_id);",
             )?;
             self.create_place_place_statement = Some(stmt);
         }
-        self.create_place_place_statement
+        let r = self
+            .create_place_place_statement
             .as_mut()
             .unwrap()
             .execute(&[(":parent_id", &parent_id), (":place_id", &place_id)])?;
-        Ok(())
+        Ok(r > 0)
     }
 
     pub fn select_all_plants(&mut self) -> Result<HashMap<String, Species>, Error> {
@@ -180,3 +527,164 @@ impl<'a> GallformersDB<'a> {
         Ok(rs)
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// the range tables as they were before the importer recorded sources, with Quercus alba in Minnesota
+    const BASE: &str = "
+        CREATE TABLE species (id INTEGER PRIMARY KEY, taxoncode TEXT, name TEXT NOT NULL UNIQUE);
+        CREATE TABLE place (id INTEGER PRIMARY KEY, name TEXT NOT NULL, code TEXT NOT NULL, type TEXT NOT NULL);
+        CREATE TABLE speciesplace (species_id INTEGER, place_id INTEGER, PRIMARY KEY (species_id, place_id));
+        INSERT INTO species VALUES (1, 'plant', 'Quercus alba');
+        INSERT INTO place VALUES (1, 'Minnesota', 'MN', 'state'), (2, 'Wisconsin', 'WI', 'state'),
+            (3, 'Iowa', 'IA', 'state'), (4, 'Ontario', 'ON', 'province');
+        INSERT INTO speciesplace VALUES (1, 1);
+    ";
+
+    const MIGRATIONS: [&str; 3] = [
+        include_str!("../../migrations/020-gallformers.sql"),
+        include_str!("../../migrations/021-gallformers.sql"),
+        include_str!("../../migrations/022-gallformers.sql"),
+    ];
+
+    /// an in-memory gallformers DB with just the range tables, migrated to the current schema
+    pub fn gf_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(BASE).unwrap();
+        for m in MIGRATIONS {
+            conn.execute_batch(m.split("-- Down").next().unwrap())
+                .unwrap();
+        }
+        conn
+    }
+
+    fn assertion(source: PlaceSource) -> PlaceAssertion<'static> {
+        PlaceAssertion {
+            source,
+            version: "1",
+            asof: "2021-10-18",
+            record: "",
+            nativity: None,
+            occurrence: None,
+        }
+    }
+
+    fn sources(gf_db: &mut GallformersDB) -> Vec<(String, Option<PlaceSource>)> {
+        gf_db
+            .select_place_sources("Quercus alba")
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.place, s.source))
+            .collect()
+    }
+
+    #[test]
+    fn test_importers_leave_curator_links_alone() {
+        let conn = gf_conn();
+        let mut gf_db = GallformersDB::new(&conn);
+
+        // the links that were there before sources were recorded are the curators'
+        assert_eq!(
+            sources(&mut gf_db),
+            vec![("Minnesota".to_string(), Some(PlaceSource::Manual))]
+        );
+
+        // a curator adds Wisconsin on the site, which does not record a source
+        conn.execute(
+            "INSERT INTO speciesplace (species_id, place_id) VALUES (1, 2);",
+            [],
+        )
+        .unwrap();
+
+        // the curators' links from before the migration are recorded as asserted by USDA too, Wisconsin is not
+        let usda = assertion(PlaceSource::Usda);
+        assert!(!gf_db.assert_place_for_plant(1, 1, &usda).unwrap());
+        assert!(!gf_db.assert_place_for_plant(1, 2, &usda).unwrap());
+        assert!(gf_db.assert_place_for_plant(1, 3, &usda).unwrap());
+        // a link that another importer added is shared
+        assert!(gf_db
+            .assert_place_for_plant(1, 4, &assertion(PlaceSource::Vascan))
+            .unwrap());
+        assert!(!gf_db.assert_place_for_plant(1, 4, &usda).unwrap());
+
+        assert_eq!(
+            sources(&mut gf_db),
+            vec![
+                ("Iowa".to_string(), Some(PlaceSource::Usda)),
+                ("Minnesota".to_string(), Some(PlaceSource::Manual)),
+                ("Minnesota".to_string(), Some(PlaceSource::Usda)),
+                ("Ontario".to_string(), Some(PlaceSource::Usda)),
+                ("Ontario".to_string(), Some(PlaceSource::Vascan)),
+                ("Wisconsin".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_keeps_curator_links() {
+        let conn = gf_conn();
+        let mut gf_db = GallformersDB::new(&conn);
+        conn.execute(
+            "INSERT INTO speciesplace (species_id, place_id) VALUES (1, 2);",
+            [],
+        )
+        .unwrap();
+        let usda = assertion(PlaceSource::Usda);
+        for place_id in 1..=4 {
+            gf_db.assert_place_for_plant(1, place_id, &usda).unwrap();
+        }
+        gf_db
+            .assert_place_for_plant(1, 4, &assertion(PlaceSource::Vascan))
+            .unwrap();
+
+        // only the links that the USDA export asserted are candidates for removal, Wisconsin is never touched
+        let sourced: Vec<String> = gf_db
+            .select_sourced_places(PlaceSource::Usda)
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, _, place)| place)
+            .collect();
+        assert_eq!(sourced, vec!["Iowa", "Minnesota", "Ontario"]);
+
+        // USDA no longer has Quercus alba anywhere. the curators' Minnesota keeps its row.
+        assert_eq!(
+            gf_db.remove_place_source(1, 1, PlaceSource::Usda).unwrap(),
+            vec![PlaceSource::Manual]
+        );
+        assert!(gf_db
+            .remove_place_source(1, 3, PlaceSource::Usda)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            gf_db.remove_place_source(1, 4, PlaceSource::Usda).unwrap(),
+            vec![PlaceSource::Vascan]
+        );
+        assert_eq!(
+            sources(&mut gf_db),
+            vec![
+                ("Minnesota".to_string(), Some(PlaceSource::Manual)),
+                ("Ontario".to_string(), Some(PlaceSource::Vascan)),
+                ("Wisconsin".to_string(), None),
+            ]
+        );
+        assert!(gf_db
+            .select_sourced_places(PlaceSource::Usda)
+            .unwrap()
+            .is_empty());
+
+        // a link that a curator confirmed after the export added it stays when the export no longer asserts it
+        gf_db.assert_place_for_plant(1, 3, &usda).unwrap();
+        conn.execute(
+            "INSERT INTO speciesplacesource (species_id, place_id, source, recorded) VALUES (1, 3, 'manual', 'now');",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            gf_db.remove_place_source(1, 3, PlaceSource::Usda).unwrap(),
+            vec![PlaceSource::Manual]
+        );
+        assert!(sources(&mut gf_db).contains(&("Iowa".to_string(), Some(PlaceSource::Manual))));
+    }
+}
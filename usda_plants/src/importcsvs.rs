@@ -1,112 +1,469 @@
+use crate::cli::{ImportOptions, Paths};
+use crate::error::{ImporterError, Location, ResultExt};
 use crate::plant::PlantCSV;
 use crate::plant::PlantName;
-use crate::plant::SpeciesType;
 use crate::plantdb::AliasType;
 use crate::plantdb::PlantDB;
+use crate::plantdb::SCHEMA_VERSION;
+use crate::quarantine::Quarantine;
+use crate::snapshot::SnapshotFile;
+use crate::util::{format_timestamp, timestamp};
+use crate::validate::{incomplete_error, print_issues, validate_csvs, Issue};
 use crate::Res;
+use csv::ByteRecord;
 use rusqlite::Connection;
 use serde_json::Map;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
-use std::path::PathBuf;
-
-pub async fn import() -> Res<()> {
-    let mut plant_db_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    plant_db_file.push("plants.db");
-    let plant_c = Connection::open(plant_db_file.as_path())?;
-    let mut plant_db = PlantDB::new(&plant_c);
-
-    // load the schema into the new DB
-    let mut sql_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    sql_file.push("plants.sql");
-    match File::open(sql_file.as_path()) {
+use std::path::{Path, PathBuf};
+
+/// the plant tables are derived entirely from the CSVs, so if they were created from an older schema they are dropped,
+/// to be rebuilt when the schema is loaded, rather than migrated. the regions are dropped with them as they are also
+/// rebuilt from the CSVs, and a plants.db from before the region codes were unique could not be re-run otherwise. the
+/// snapshots are kept.
+fn drop_outdated_tables(plant_c: &Connection, plants_db: &Path, states: &[String]) -> Res<()> {
+    let version: i64 = plant_c.query_row("PRAGMA user_version;", [], |r| r.get(0))?;
+    let has_plants: bool = plant_c.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name IN ('plant', 'region'));",
+        [],
+        |r| r.get(0),
+    )?;
+    if has_plants && version < SCHEMA_VERSION {
+        if !states.is_empty() {
+            return Err(format!(
+                "{:?} was created by an older version of the importer. Run a full import, without --state, to rebuild it.",
+                plants_db
+            )
+            .into());
+        }
+        println!(
+            "{:?} was created by an older version of the importer, rebuilding the plant tables.",
+            plants_db
+        );
+        plant_c.execute_batch(
+            "DROP VIEW IF EXISTS plantstatus;
+             DROP VIEW IF EXISTS plantcommonname;
+             DROP TABLE IF EXISTS plantalias;
+             DROP TABLE IF EXISTS plantregion;
+             DROP TABLE IF EXISTS plantparent;
+             DROP TABLE IF EXISTS alias;
+             DROP TABLE IF EXISTS plant;
+             DROP TABLE IF EXISTS region;",
+        )?;
+    }
+    Ok(())
+}
+
+/// opens the plants DB, creating it if need be, with the current schema. `states` are the states that are about to be
+/// imported, see `drop_outdated_tables`.
+pub fn open_plants_db(paths: &Paths, states: &[String]) -> Res<Connection> {
+    let plant_c = Connection::open(&paths.plants_db)?;
+    drop_outdated_tables(&plant_c, &paths.plants_db, states)?;
+
+    // load the schema into the DB. it only creates what is missing so it is safe to run against an existing DB.
+    match File::open(&paths.schema) {
         Ok(mut file) => {
             let mut sql = String::new();
             file.read_to_string(&mut sql).unwrap();
-            plant_db.conn.execute_batch(&sql)?;
+            plant_c.execute_batch(&sql)?;
         }
         Err(e) => {
-            println!("Failed to open database schema {:?}. {}", sql_file, e);
+            println!("Failed to open database schema {:?}. {}", paths.schema, e);
         }
     }
+    plant_c.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(plant_c)
+}
 
-    let mut region_json = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    region_json.push("regions.json");
-    let parsed: Value = serde_json::from_str(&std::fs::read_to_string(region_json)?)?;
-    let region_map: Map<String, Value> = parsed.as_object().unwrap().clone();
-    let mut csv_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    csv_dir.push("data");
+pub async fn import(paths: &Paths, options: &ImportOptions) -> Res<()> {
+    let mut plant_c = open_plants_db(paths, &options.states)?;
 
-    let mut failures = Vec::new();
-    plant_db.conn.execute_batch("BEGIN TRANSACTION;")?;
-    for csv_file in csv_dir.read_dir()? {
-        let csv = csv_file?.path();
-        let file = File::open(&csv)?;
-        let region_abbr = csv
-            .file_stem()
-            .ok_or("Missing Region")?
-            .to_str()
-            .ok_or("Invalid unicode in filename")?;
-        let region_name = region_map
-            .get(region_abbr)
-            .ok_or("Unknown Region")?
-            .as_str()
-            .ok_or("Unknown Region")?;
-        let region_id = plant_db.create_region(region_name, region_abbr)?;
+    let parsed: Value = serde_json::from_str(&std::fs::read_to_string(&paths.regions)?)?;
+    let region_map: Map<String, Value> = parsed.as_object().unwrap().clone();
+    let csvs = select_csvs(&paths.data_dir, &options.states)?;
 
-        println!(
-            "Processing {:?} with abbr {} and name {}.",
-            csv.to_str(),
-            region_abbr,
-            region_name
-        );
-        let mut rdr = csv::Reader::from_reader(file);
-        for r in rdr.deserialize() {
-            let plant: PlantCSV = r?;
-            let raw_name = plant.name.to_string();
-            // println!("{:?}", plant.name.to_string());
-            let name_result = PlantName::new(raw_name);
-            if let Ok(name) = name_result {
-                if name.species_type == SpeciesType::OrthVar
-                    || name.species_type == SpeciesType::Other
-                {
-                    // for now we are going to skip all of the weird taxonomy naming  variations and related
+    // every CSV is checked, not just the selected ones, so that a state can be compared to all of the others
+    let previous = PlantDB::new(&plant_c).select_latest_snapshot_rows()?;
+    let issues: Vec<(String, Issue)> =
+        validate_csvs(&select_csvs(&paths.data_dir, &[])?, &region_map, &previous)?
+            .into_iter()
+            .filter(|(code, issue)| {
+                // a missing CSV only matters when importing everything
+                if options.states.is_empty() {
+                    true
                 } else {
-                    let id = plant_db.create_plant(&plant, name)?;
-                    if !plant.common_name.trim().is_empty() {
-                        let cn_id = plant_db.create_alias(&plant.common_name)?;
-                        plant_db.relate_alias_to_plant(
-                            &cn_id.to_string(),
-                            AliasType::Common,
-                            &id.to_string(),
-                        )?;
-                    }
-                    plant_db.create_plant_region(&id.to_string(), &region_id.to_string())?;
+                    issue.is_incomplete()
+                        && options.states.iter().any(|s| s.eq_ignore_ascii_case(code))
                 }
-            } else {
-                failures.push(plant.name.to_string());
-            }
+            })
+            .collect();
+    print_issues(&issues);
+    if options.strict {
+        if let Some(e) = incomplete_error(&issues) {
+            return Err(e);
         }
     }
-    plant_db.conn.execute_batch("END TRANSACTION;")?;
+
+    let mut failures = Vec::new();
+    let mut quarantine = Quarantine::new();
+    // if anything fails the transaction is rolled back when it is dropped, leaving an existing DB as it was
+    let tx = plant_c.transaction()?;
+    {
+        let mut plant_db = PlantDB::new(&tx);
+        let downloaded = match &options.downloaded {
+            Some(d) => d.clone(),
+            None => newest_modified(&csvs)?,
+        };
+        let snapshot_id = plant_db.create_snapshot(&timestamp(), &downloaded)?;
+        for csv in csvs {
+            import_csv(
+                &mut plant_db,
+                snapshot_id,
+                &csv,
+                &region_map,
+                options,
+                &mut failures,
+                &mut quarantine,
+            )?;
+        }
+        let linked = plant_db.link_plant_parents()?;
+        println!("Linked {} hybrid parents to their plants.", linked);
+        let linked = plant_db.link_synonyms()?;
+        println!("Linked {} synonyms to their accepted taxa.", linked);
+        println!(
+            "Recorded the import as snapshot {} of the checklist downloaded {}.",
+            snapshot_id, downloaded
+        );
+    }
+    tx.commit()?;
 
     if !failures.is_empty() {
-        let parse_failures_filename = "parse_failures.txt";
+        // write the failures next to the plants DB so that runs against different DBs do not clobber each other
+        let p = paths.plants_db.with_file_name("parse_failures.txt");
         println!(
-            "There were {} items that were not parseable into Plants. Check {} for details.",
-            parse_failures_filename,
-            failures.len()
+            "There were {} items that were not parseable into Plants. Check {:?} for details.",
+            failures.len(),
+            p
         );
 
-        let mut p = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        p.push(parse_failures_filename);
         let mut file = File::create(p)?;
         for f in failures {
             writeln!(&mut file, "{}", f)?;
         }
     }
 
+    if !quarantine.is_empty() {
+        let p = options
+            .quarantine
+            .clone()
+            .unwrap_or_else(|| paths.plants_db.with_file_name("quarantine.csv"));
+        quarantine.write_csv(&p)?;
+        quarantine.print_summary();
+        println!("The malformed records were written to {:?}.", p);
+    }
+
+    Ok(())
+}
+
+/// lists the CSV files in the data directory, limited to the given states if there are any.
+fn select_csvs(data_dir: &Path, states: &[String]) -> Res<Vec<PathBuf>> {
+    let mut csvs = Vec::new();
+    for csv_file in data_dir.read_dir()? {
+        csvs.push(csv_file?.path());
+    }
+    csvs.sort();
+    if states.is_empty() {
+        return Ok(csvs);
+    }
+
+    let mut selected = Vec::new();
+    for state in states {
+        let csv = csvs
+            .iter()
+            .find(|c| {
+                c.file_stem()
+                    .map(|s| s.to_string_lossy().eq_ignore_ascii_case(state))
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| format!("There is no CSV for {} in {:?}.", state, data_dir))?;
+        selected.push(csv.clone());
+    }
+    Ok(selected)
+}
+
+/// imports a single state's CSV. any links between plants and the state from an earlier import are replaced, so
+/// importing a fresh copy of a CSV drops the state from plants that are no longer listed in it.
+fn import_csv(
+    plant_db: &mut PlantDB,
+    snapshot_id: i64,
+    csv: &Path,
+    region_map: &Map<String, Value>,
+    options: &ImportOptions,
+    failures: &mut Vec<ImporterError>,
+    quarantine: &mut Quarantine,
+) -> Res<()> {
+    let file_at = Location::file(csv);
+    // the whole file is read up front so that the exact bytes of any malformed record can be reported
+    let bytes = std::fs::read(csv).at(&file_at)?;
+    let region_abbr = csv
+        .file_stem()
+        .ok_or("Missing Region")
+        .at(&file_at)?
+        .to_str()
+        .ok_or("Invalid unicode in filename")
+        .at(&file_at)?;
+    let region_name = region_map
+        .get(region_abbr)
+        .and_then(|r| r.as_str())
+        .ok_or_else(|| ImporterError::UnknownRegion {
+            at: file_at.clone(),
+            code: region_abbr.to_string(),
+        })?;
+    let region_id = plant_db
+        .create_or_fetch_region(region_name, region_abbr)
+        .at(&file_at)?;
+    let cleared = plant_db.delete_plant_regions(region_id).at(&file_at)?;
+
+    println!(
+        "Processing {:?} with abbr {} and name {}.",
+        csv.to_str(),
+        region_abbr,
+        region_name
+    );
+    if cleared > 0 {
+        println!("Replacing {} plants from an earlier import.", cleared);
+    }
+    // records with the wrong number of fields are checked for in decode_record so that they can be quarantined
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(bytes.as_slice());
+    let headers = rdr.byte_headers().at(&file_at)?.clone();
+    let mut record = ByteRecord::new();
+    let mut rows = 0;
+    while rdr.read_byte_record(&mut record).at(&file_at)? {
+        rows += 1;
+        let (start, line) = record
+            .position()
+            .map(|p| (p.byte() as usize, p.line()))
+            .unwrap_or((0, 0));
+        let raw = trim_line_end(&bytes[start..rdr.position().byte() as usize]);
+        let at = Location::row(csv, line, &String::from_utf8_lossy(raw));
+        let plant = match decode_record(&record, &headers).at(&at) {
+            Ok(plant) => plant,
+            Err(e) if !options.strict => {
+                quarantine.add(region_abbr, &e, raw);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        plant_db
+            .create_snapshot_record(snapshot_id, region_abbr, &plant)
+            .at(&at)?;
+        let raw_name = plant.name.to_string();
+        let name_result = PlantName::new(raw_name);
+        // names with a nomenclatural status, e.g., nom. illeg., are stored too. the plantstatus view links them to the
+        // accepted taxon.
+        if let Ok(name) = name_result {
+            let id = plant_db.create_plant(&plant, name).at(&at)?;
+            if !plant.common_name.trim().is_empty() {
+                let cn_id = plant_db.create_alias(&plant.common_name).at(&at)?;
+                plant_db
+                    .relate_alias_to_plant(
+                        &cn_id.to_string(),
+                        AliasType::Common,
+                        &id.to_string(),
+                        &region_id.to_string(),
+                    )
+                    .at(&at)?;
+            }
+            plant_db
+                .create_plant_region(&id.to_string(), &region_id.to_string())
+                .at(&at)?;
+        } else {
+            failures.push(ImporterError::NameParse {
+                at,
+                name: plant.name.to_string(),
+            });
+        }
+    }
+
+    let file = SnapshotFile {
+        region_code: region_abbr.to_string(),
+        filename: csv
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        sha256: sha256_hex(&bytes),
+        rows,
+    };
+    plant_db
+        .create_snapshot_file(snapshot_id, &file)
+        .at(&file_at)?;
+
     Ok(())
 }
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// when the newest of the files was last modified, which is the best guess at when they were downloaded
+fn newest_modified(files: &[PathBuf]) -> Res<String> {
+    let mut newest = 0;
+    for f in files {
+        let modified = std::fs::metadata(f)?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        newest = newest.max(modified);
+    }
+    Ok(format_timestamp(newest))
+}
+
+/// decodes a raw CSV record into a PlantCSV, failing if it does not have exactly one field per header.
+fn decode_record(record: &ByteRecord, headers: &ByteRecord) -> Res<PlantCSV> {
+    if record.len() != headers.len() {
+        return Err(ImporterError::FieldCount {
+            at: Default::default(),
+            expected: headers.len(),
+            found: record.len(),
+        });
+    }
+    Ok(record.deserialize(Some(headers))?)
+}
+
+fn trim_line_end(raw: &[u8]) -> &[u8] {
+    let end = raw
+        .iter()
+        .rposition(|b| *b != b'\n' && *b != b'\r')
+        .map_or(0, |i| i + 1);
+    &raw[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// plants.sql as it was before the importer could be re-run against an existing plants.db
+    const BASELINE_SCHEMA: &str = r#"
+        CREATE TABLE alias (
+            id   INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT    NOT NULL UNIQUE
+        );
+        CREATE TABLE plant (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            rawname       TEXT    NOT NULL UNIQUE,
+            symbol        TEXT    NOT NULL,
+            symbolsynonym TEXT    NOT NULL DEFAULT "",
+            family        TEXT    NOT NULL,
+            genus         TEXT    NOT NULL,
+            specific      TEXT    NOT NULL,
+            type          TEXT    NOT NULL CHECK (type IN ("ssp.", "var.", "sp.", "x") ),
+            sspvar        TEXT    NOT NULL DEFAULT "",
+            hybridpair    TEXT    NOT NULL DEFAULT "",
+            author        TEXT    NOT NULL,
+            secondauthor  TEXT    NOT NULL DEFAULT ""
+        );
+        CREATE TABLE plantalias (
+            plant_id  INTEGER REFERENCES plant (id) ON DELETE CASCADE,
+            alias_id  INTEGER REFERENCES alias (id) ON DELETE CASCADE,
+            type      TEXT NOT NULL CHECK (type IN ("common", "orth. var.") )
+        );
+        CREATE TABLE plantregion (
+            plant_id   INTEGER REFERENCES plant (id) ON DELETE CASCADE NOT NULL,
+            region_id  INTEGER REFERENCES region (id) ON DELETE CASCADE NOT NULL
+        );
+        CREATE TABLE region (
+            id   INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            name TEXT    NOT NULL,
+            code TEXT    NOT NULL
+        );
+        INSERT INTO region (name, code) VALUES ("Alabama", "AL"), ("Alabama", "AL");
+        INSERT INTO plant (rawname, symbol, family, genus, specific, type, author)
+            VALUES ("Quercus alba L.", "QUAL", "Fagaceae", "Quercus", "alba", "sp.", "L.");
+        INSERT INTO plantregion VALUES (1, 1);
+    "#;
+
+    #[test]
+    fn test_upgrade_baseline_db() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(BASELINE_SCHEMA).unwrap();
+        let db = PathBuf::from("plants.db");
+
+        // a single state can not be imported into the old tables
+        assert!(drop_outdated_tables(&conn, &db, &["AL".to_string()]).is_err());
+
+        drop_outdated_tables(&conn, &db, &[]).unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut plant_db = PlantDB::new(&conn);
+        let al = plant_db.create_or_fetch_region("Alabama", "AL").unwrap();
+        assert_eq!(
+            plant_db.create_or_fetch_region("Alabama", "AL").unwrap(),
+            al
+        );
+
+        let p = PlantCSV {
+            symbol: "QUAL".to_string(),
+            syn_symbol: "".to_string(),
+            name: "Quercus alba L.".to_string(),
+            common_name: "white oak".to_string(),
+            family: "Fagaceae".to_string(),
+        };
+        let id = plant_db
+            .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+            .unwrap();
+        plant_db
+            .create_plant_region(&id.to_string(), &al.to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_decode_record() {
+        let headers = ByteRecord::from(vec![
+            "Symbol",
+            "Synonym Symbol",
+            "Scientific Name with Author",
+            "State Common Name",
+            "Family",
+        ]);
+        let plant = decode_record(
+            &ByteRecord::from(vec!["QUAL", "", "Quercus alba L.", "white oak", "Fagaceae"]),
+            &headers,
+        )
+        .unwrap();
+        assert_eq!(plant.name, "Quercus alba L.");
+
+        let e = decode_record(
+            &ByteRecord::from(vec!["QUAL", "", "Quercus alba L."]),
+            &headers,
+        )
+        .unwrap_err();
+        assert_eq!(e.exit_code(), 3);
+
+        let mut bad = ByteRecord::from(vec!["QUAL", "", "Quercus alba L.", "white oak"]);
+        bad.push_field(b"Fag\xffceae");
+        assert_eq!(decode_record(&bad, &headers).unwrap_err().exit_code(), 3);
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_trim_line_end() {
+        assert_eq!(trim_line_end(b"\"a\",\"b\"\r\n"), b"\"a\",\"b\"");
+        assert_eq!(trim_line_end(b"\"a\""), b"\"a\"");
+        assert_eq!(trim_line_end(b"\n"), b"");
+    }
+}
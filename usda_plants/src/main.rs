@@ -1,41 +1,77 @@
+use crate::backup::restore;
+use crate::cli::{Cli, Command, Source};
 use crate::exporttogf::export;
 use crate::importcsvs::import;
+use crate::provenance::provenance;
+use crate::reconcile::reconcile;
+use crate::snapshot::{diff_snapshots, list_snapshots, prune_snapshots};
 use crate::util::Res;
+use crate::validate::validate;
 use crate::vascan::vascan_export;
 use crate::vascan::vascan_import;
-use std::env;
+use clap::Parser;
 
 extern crate nom;
 
+pub mod backup;
+pub mod cli;
+pub mod error;
 pub mod exporttogf;
 pub mod gallformersdb;
 pub mod importcsvs;
+pub mod nativity;
 pub mod plant;
 pub mod plantdb;
+pub mod provenance;
+pub mod quarantine;
+pub mod reconcile;
+pub mod report;
+pub mod snapshot;
 pub mod species;
 pub mod util;
+pub mod validate;
 pub mod vascan;
 
-fn help(args: Vec<String>) -> Res<()> {
-    println!("Pass in a command line argument of `import` to import the USDA plant CSVs into a new database, `export` to export         previously imported plant data into the main gallformers database, or `both` to do both in order. {:?}", args);
-    Ok(())
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        if let Some(raw) = &e.location().raw {
+            eprintln!("  record: {}", raw);
+        }
+        if let Some(hint) = e.hint() {
+            eprintln!("  hint: {}", hint);
+        }
+        std::process::exit(e.exit_code());
+    }
 }
 
-#[tokio::main]
-async fn main() -> Res<()> {
-    let args: Vec<String> = env::args().collect();
+async fn run(cli: Cli) -> Res<()> {
+    let paths = &cli.paths;
 
-    match args[1].as_str() {
-        "import" => match args[2].as_str() {
-            "usda" => import().await,
-            "vascan" => vascan_import().await,
-            _ => help(args),
+    match cli.command {
+        Command::Import { source, options } => match source {
+            Source::Usda => import(paths, &options).await,
+            Source::Vascan => vascan_import(paths, &options).await,
         },
-        "export" => match args[2].as_str() {
-            "usda" => export().await,
-            "vascan" => vascan_export().await,
-            _ => help(args),
+        Command::Export { source, options } => match source {
+            Source::Usda => export(paths, &options).await,
+            Source::Vascan => vascan_export(paths, &options).await,
         },
-        _ => help(args),
+        Command::Both {
+            import: i,
+            export: e,
+        } => {
+            import(paths, &i).await?;
+            export(paths, &e).await
+        }
+        Command::Validate => validate(paths),
+        Command::Snapshots => list_snapshots(paths),
+        Command::Prune { keep } => prune_snapshots(paths, keep as usize),
+        Command::Diff { old, new } => diff_snapshots(paths, old, new),
+        Command::Restore { backup } => restore(paths, backup.as_deref()),
+        Command::Reconcile(options) => reconcile(paths, &options),
+        Command::Provenance { host } => provenance(paths, &host),
     }
 }
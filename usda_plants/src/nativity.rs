@@ -0,0 +1,94 @@
+use clap::ValueEnum;
+use strum_macros::{Display, EnumString};
+
+/// whether a plant is native to a place. only VASCAN says this per province, USDA's per state downloads have no native
+/// status so the USDA ranges are exported without one.
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Hash)]
+#[strum(serialize_all = "lowercase")]
+pub enum Nativity {
+    Native,
+    Introduced,
+    /// introduced and spreading to the detriment of the native plants. neither USDA nor VASCAN say which
+    /// plants are invasive so this is only ever set by a curator, and is never overwritten by an import.
+    Invasive,
+}
+
+impl Nativity {
+    /// reads VASCAN's `establishmentMeans`. ephemeral plants are introduced ones that do not persist.
+    pub fn from_vascan(establishment_means: &str) -> Option<Self> {
+        match establishment_means.to_lowercase().as_str() {
+            "native" => Some(Nativity::Native),
+            "introduced" | "ephemeral" => Some(Nativity::Introduced),
+            _ => None,
+        }
+    }
+}
+
+/// VASCAN's status for a plant in a province or territory. only some of them mean that the plant is there, which ones
+/// count as its range is up to the export, see `Occurrence::RANGE`.
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Hash, ValueEnum)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Occurrence {
+    /// there, without saying whether it is native
+    Present,
+    Native,
+    Introduced,
+    /// introduced but does not persist
+    Ephemeral,
+    /// reported but the reports are not trusted
+    Doubtful,
+    /// reported in error
+    Excluded,
+    /// was there but no longer is
+    Extirpated,
+}
+
+impl Occurrence {
+    /// the statuses that are exported as range when none are given: the ones that say the plant is there now, so not
+    /// doubtful, excluded or extirpated
+    pub const RANGE: [Occurrence; 4] = [
+        Occurrence::Present,
+        Occurrence::Native,
+        Occurrence::Introduced,
+        Occurrence::Ephemeral,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vascan() {
+        assert_eq!(Nativity::from_vascan("native"), Some(Nativity::Native));
+        assert_eq!(
+            Nativity::from_vascan("Introduced"),
+            Some(Nativity::Introduced)
+        );
+        assert_eq!(
+            Nativity::from_vascan("ephemeral"),
+            Some(Nativity::Introduced)
+        );
+        assert_eq!(Nativity::from_vascan(""), None);
+    }
+
+    #[test]
+    fn test_nativity() {
+        assert_eq!("invasive".parse::<Nativity>().unwrap(), Nativity::Invasive);
+        assert_eq!(Nativity::Introduced.to_string(), "introduced");
+    }
+
+    #[test]
+    fn test_occurrence() {
+        assert_eq!("doubtful".parse::<Occurrence>(), Ok(Occurrence::Doubtful));
+        assert_eq!(
+            "Extirpated".parse::<Occurrence>(),
+            Ok(Occurrence::Extirpated)
+        );
+        assert!("absent".parse::<Occurrence>().is_err());
+        assert_eq!(Occurrence::Ephemeral.to_string(), "ephemeral");
+        assert!(!Occurrence::RANGE.contains(&Occurrence::Doubtful));
+        assert!(!Occurrence::RANGE.contains(&Occurrence::Excluded));
+        assert!(!Occurrence::RANGE.contains(&Occurrence::Extirpated));
+    }
+}
@@ -1,7 +1,8 @@
-use crate::plant::parsers::parse_name;
+use crate::plant::parsers::{find_status, parse_authorship, parse_name, parse_name_tolerant};
 use crate::species::SpeciesName;
 use serde_derive::Deserialize;
-use strum_macros::Display;
+use std::fmt;
+use strum_macros::{Display, EnumIter, EnumString};
 
 /// struct that the CSV rows will be deserialized into
 /// a CSV row is: "Symbol","Synonym Symbol","Scientific Name with Author","State Common Name","Family"
@@ -30,31 +31,175 @@ pub struct Plant {
 
 const VARIETY: &str = " var. ";
 const SUBSPECIES: &str = " ssp. ";
-const ORTH_VAR: &str = ", orth. var.";
+const SUBVARIETY: &str = " subvar. ";
+const FORM: &str = " f. ";
+const SUBFORM: &str = " subf. ";
+const STATUS_START: &str = ", ";
 const HYBRID_START: &str = " [";
 
-#[derive(Clone, Display, Debug, PartialEq)]
+#[derive(Clone, Copy, Default, Display, EnumString, Debug, PartialEq, Eq, Hash)]
 pub enum SpeciesType {
     #[strum(serialize = "x")]
     Hybrid,
+    #[default]
     #[strum(serialize = "sp.")]
     Species,
     #[strum(serialize = "ssp.")]
     Subspecies,
     #[strum(serialize = "var.")]
     Variety,
-    #[strum(serialize = "orth. var.")]
-    OrthVar,
-    // other will be used if we encounter any of the taxonomy naming stuff like:
-    // orth. cons. / orth. rej. / orth. var., nom. inval. / nom. inval. / nom. utique rej. / nom. illeg. / nom. nud. / nom/ inq.
-    #[strum(serialize = "other")]
-    Other,
+    #[strum(serialize = "subvar.")]
+    Subvariety,
+    #[strum(serialize = "f.")]
+    Form,
+    #[strum(serialize = "subf.")]
+    Subform,
+}
+
+/// an infraspecific rank above the rank of the name itself, e.g., `ssp. americana` in
+/// `Agave americana L. ssp. americana var. expansa (Jacobi) Gentry`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InfraRank {
+    pub rank: SpeciesType,
+    pub epithet: String,
+    pub author: Option<String>,
+}
+
+impl InfraRank {
+    pub fn authorship(&self) -> Option<Authorship> {
+        self.author.as_deref().map(Authorship::parse)
+    }
+}
+
+/// one part of an authorship, e.g., `Douglas ex D. Don` in `(Douglas ex D. Don) Lindl.`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Authors {
+    /// the authors that validly published the name
+    pub authors: Vec<String>,
+    /// the authors that the name is ascribed to but who did not validly publish it, the ones before ` ex `
+    pub ex_authors: Vec<String>,
+    /// the authors of the work that the name was published in, the ones after ` in `
+    pub in_authors: Vec<String>,
+    /// the authors that sanctioned the name, the ones after ` : `
+    pub sanctioning: Vec<String>,
+}
+
+/// an author string split into its parts, e.g., `(J.F. Gmel.) Steud.`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Authorship {
+    /// the authors of the basionym, the ones in parentheses
+    pub basionym: Option<Authors>,
+    /// the authors of the combination, or of the name if there is no basionym
+    pub combining: Option<Authors>,
+    /// anything that qualifies the authors rather than being one of them, e.g., `(pro sp.)`, `non L.` or `auct. p.p.`
+    pub qualifier: Option<String>,
+}
+
+impl Authorship {
+    /// splits an author string into its parts. anything that does not look like authors ends up in the qualifier.
+    pub fn parse(raw: &str) -> Self {
+        parse_authorship(raw)
+    }
+
+    /// true for a name that was applied to the wrong taxon rather than being a synonym of it, e.g., `auct. non Benth.`
+    /// or `sensu Small`
+    pub fn is_misapplied(&self) -> bool {
+        self.qualifier
+            .as_deref()
+            .is_some_and(|q| q.starts_with("auct.") || q.starts_with("sensu "))
+    }
+
+    /// true if both name the same basionym and combining authors, ignoring who the names are ascribed to, where they
+    /// were published and any qualifiers
+    pub fn same_authors(&self, other: &Authorship) -> bool {
+        let authors = |a: &Option<Authors>| a.as_ref().map(|a| a.authors.clone());
+        authors(&self.basionym) == authors(&other.basionym)
+            && authors(&self.combining) == authors(&other.combining)
+    }
 }
 
-impl Default for SpeciesType {
-    fn default() -> Self {
-        SpeciesType::Species
+fn write_team(f: &mut fmt::Formatter<'_>, team: &[String]) -> fmt::Result {
+    for (n, author) in team.iter().enumerate() {
+        if n > 0 {
+            f.write_str(if n == team.len() - 1 { " & " } else { ", " })?;
+        }
+        f.write_str(author)?;
     }
+    Ok(())
+}
+
+impl fmt::Display for Authors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.ex_authors.is_empty() {
+            write_team(f, &self.ex_authors)?;
+            f.write_str(" ex ")?;
+        }
+        write_team(f, &self.authors)?;
+        if !self.in_authors.is_empty() {
+            f.write_str(" in ")?;
+            write_team(f, &self.in_authors)?;
+        }
+        if !self.sanctioning.is_empty() {
+            f.write_str(" : ")?;
+            write_team(f, &self.sanctioning)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Authorship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        if let Some(b) = &self.basionym {
+            write!(f, "({})", b)?;
+            sep = " ";
+        }
+        if let Some(c) = &self.combining {
+            write!(f, "{}{}", sep, c)?;
+            sep = " ";
+        }
+        if let Some(q) = &self.qualifier {
+            // only the parenthetical and misapplied qualifiers are not set off by a comma, e.g., `L. (pro sp.)`
+            if !sep.is_empty() && !["(", "auct.", "sensu "].iter().any(|p| q.starts_with(p)) {
+                sep = ", ";
+            }
+            write!(f, "{}{}", sep, q)?;
+        }
+        Ok(())
+    }
+}
+
+/// the nomenclatural status that USDA appends to some names, e.g., `Acacia vernicosa Standl., nom. illeg.`
+#[derive(Clone, Copy, Display, EnumIter, Debug, PartialEq, Eq)]
+pub enum NomStatus {
+    #[strum(serialize = "orth. var.")]
+    OrthVar,
+    #[strum(serialize = "orth. cons.")]
+    OrthCons,
+    #[strum(serialize = "orth. rej.")]
+    OrthRej,
+    #[strum(serialize = "nom. cons.")]
+    NomCons,
+    #[strum(serialize = "nom. rej.")]
+    NomRej,
+    #[strum(serialize = "nom. utique rej.")]
+    NomUtiqueRej,
+    #[strum(serialize = "nom. inval.")]
+    NomInval,
+    #[strum(serialize = "nom. illeg.")]
+    NomIlleg,
+    #[strum(serialize = "nom. nud.")]
+    NomNud,
+    #[strum(serialize = "nom. inq.")]
+    NomInq,
+    #[strum(serialize = "nom. dub.")]
+    NomDub,
+    #[strum(serialize = "nom. obsc.")]
+    NomObsc,
+    #[strum(serialize = "nom. ambig.")]
+    NomAmbig,
+    #[strum(serialize = "excl. type")]
+    ExclType,
 }
 
 //TODO: convert to From trait?
@@ -62,33 +207,161 @@ pub fn plant_name_to_species_name(pn: &PlantName) -> SpeciesName {
     SpeciesName {
         genus: pn.genus.to_string(),
         specific: pn.specific.to_string(),
+        rank: pn.species_type,
         ssp: pn.sspvar.clone(),
-        hybrid: pn.hybrid.is_some(),
+        hybrid: pn.nothospecies || pn.species_type == SpeciesType::Hybrid,
     }
 }
 
+/// the ways a `PlantName` can be written out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameStyle {
+    /// the name without authors, e.g., `Quercus ×beadlei` or `Agave americana ssp. americana var. expansa`
+    Canonical,
+    /// the name as USDA writes it, with authors, status and hybrid formula,
+    /// e.g., `Quercus ×beadlei Trel. ex Palmer (pro sp.) [alba × michauxii]`
+    Full,
+    /// the name as gallformers writes it, e.g., `Quercus x leana` or `Acer saccharum var. nigrum`. only the rank of
+    /// the name itself is kept for quadrinomials.
+    Gallformers,
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct PlantName {
     pub genus: String,
     pub specific: String,
     pub species_type: SpeciesType,
     pub sspvar: Option<String>,
-    pub hybrid: Option<(String, String)>,
     pub author: Option<String>,
     pub second_author: Option<String>,
+    pub status: Option<NomStatus>,
+    /// the ranks between the species and the rank of the name, outermost first. only quadrinomials have any.
+    pub ranks: Vec<InfraRank>,
+    /// a hybrid genus, e.g., `×Agropogon`. the × is not part of the genus.
+    pub nothogenus: bool,
+    /// a hybrid species, e.g., `Quercus ×beadlei`, or an infraspecific name in one. the × is not part of the specific.
+    pub nothospecies: bool,
+    /// the text in brackets after a hybrid, which is usually its formula, e.g., `alba × michauxii`
+    pub formula: Option<String>,
+    /// the parents of a hybrid, from its formula. parents without a genus are in the genus of the hybrid.
+    pub parents: Vec<PlantName>,
 }
 
-impl<'a> PlantName {
+impl PlantName {
     pub fn new(name: String) -> Result<Self, nom::error::Error<String>> {
         parse_name(&name)
     }
 
+    /// parses a name that was written by hand rather than coming from USDA, e.g., a gallformers host. an ASCII `x` can
+    /// be used for the hybrid sign, e.g., `Quercus x leana`, the authors can be left out, and the name can be just a
+    /// genus, e.g., `Quercus` or `Quercus sp.`, in which case the specific is empty.
+    pub fn parse_tolerant(name: &str) -> Result<Self, nom::error::Error<String>> {
+        parse_name_tolerant(name)
+    }
+
+    pub fn authorship(&self) -> Option<Authorship> {
+        self.author.as_deref().map(Authorship::parse)
+    }
+
+    pub fn second_authorship(&self) -> Option<Authorship> {
+        self.second_author.as_deref().map(Authorship::parse)
+    }
+
+    /// true if the two names have the same authors, by `Authorship::same_authors`, for the name and every rank in it
+    pub fn same_authors(&self, other: &PlantName) -> bool {
+        let same = |a: Option<Authorship>, b: Option<Authorship>| match (a, b) {
+            (Some(a), Some(b)) => a.same_authors(&b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        same(self.authorship(), other.authorship())
+            && same(self.second_authorship(), other.second_authorship())
+            && self.ranks.len() == other.ranks.len()
+            && self
+                .ranks
+                .iter()
+                .zip(&other.ranks)
+                .all(|(a, b)| same(a.authorship(), b.authorship()))
+    }
+
     pub fn species_name(&mut self) -> String {
         format!("{} {}", self.genus, self.specific)
     }
+
+    /// true for a hybrid that has no name of its own, only a formula, e.g., `Pinus rigida × taeda`
+    fn is_formula_name(&self) -> bool {
+        self.species_type == SpeciesType::Hybrid && self.specific.contains(" × ")
+    }
+
+    pub fn render(&self, style: NameStyle) -> String {
+        let hybrid = match style {
+            NameStyle::Gallformers => "x ",
+            _ => "×",
+        };
+        let with_authors = style == NameStyle::Full;
+        let add_author = |name: &mut String, author: &Option<String>| {
+            if let Some(a) = author.as_deref().filter(|a| with_authors && !a.is_empty()) {
+                name.push(' ');
+                name.push_str(a);
+            }
+        };
+
+        let mut name = String::new();
+        if self.nothogenus {
+            name.push_str(hybrid);
+        }
+        name.push_str(&self.genus);
+        name.push(' ');
+        if self.is_formula_name() {
+            name.push_str(
+                &self
+                    .specific
+                    .replace(" × ", &format!(" {} ", hybrid.trim())),
+            );
+            if with_authors && self.formula.as_deref() != Some(self.specific.as_str()) {
+                if let Some(f) = &self.formula {
+                    name.push_str(&format!(" [{}]", f));
+                }
+            }
+            return name;
+        }
+        if self.nothospecies {
+            name.push_str(hybrid);
+        }
+        name.push_str(&self.specific);
+        add_author(&mut name, &self.author);
+        if style != NameStyle::Gallformers {
+            for r in &self.ranks {
+                name.push_str(&format!(" {} {}", r.rank, r.epithet));
+                add_author(&mut name, &r.author);
+            }
+        }
+        if let Some(sspvar) = &self.sspvar {
+            name.push_str(&format!(" {} {}", self.species_type, sspvar));
+        }
+        add_author(&mut name, &self.second_author);
+        if with_authors {
+            if let Some(s) = self.status {
+                // a second status, e.g., `, orth. var., nom. inval.`, is parsed as part of the last author so the first
+                // one has to go back in front of it
+                let at = find_status(&name).unwrap_or(name.len());
+                name.insert_str(at, &format!(", {}", s));
+            }
+            if let Some(f) = &self.formula {
+                name.push_str(&format!(" [{}]", f));
+            }
+        }
+        name
+    }
+}
+
+/// the full name, as USDA writes it
+impl fmt::Display for PlantName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(NameStyle::Full))
+    }
 }
 
-pub(self) mod parsers {
+mod parsers {
     use super::*;
     use nom::branch::*;
     use nom::bytes::complete::*;
@@ -98,6 +371,7 @@ pub(self) mod parsers {
     use nom::sequence::*;
     use nom::Finish;
     use nom::IResult;
+    use strum::IntoEnumIterator;
 
     fn not_whitespace(i: &str) -> IResult<&str, &str> {
         is_not(" \t")(i)
@@ -109,109 +383,445 @@ pub(self) mod parsers {
         take_while(|c| c == '×')(i)
     }
 
-    // Parse the pair of species for a hybrid: [species1 × species2]
+    // Parse the bracketed text after a hybrid, e.g., ` [alba × michauxii]`. Usually it is the hybrid formula but it can
+    // be anything, e.g., ` [unnamed hybrid]`.
+    fn brackets(i: &str) -> IResult<&str, &str> {
+        delimited(tag(HYBRID_START), is_not("]"), tag("]"))(i)
+    }
+
+    // An epithet in a hybrid formula. `?` and `sp.` are used for parents that are not known.
+    fn formula_epithet(i: &str) -> IResult<&str, &str> {
+        is_not(" ×()[]")(i)
+    }
+
+    // Parse a hybrid formula with two or more parents: species1 × species2
     // N.B., the symbol is not an ASCII x but × the unicode multiplcation sign. https://codepoints.net/U+00d7
-    // Anything but a comma can be part of the names separated by the hybrid symbol.
-    fn hybrid(i: &str) -> IResult<&str, (&str, &str)> {
-        //TODO this is hideous and there has got to be a better way
-        let p = separated_pair(
-            preceded(tag(HYBRID_START), take_while1(|c| c != '×')),
-            tag("×"),
-            terminated(take_while1(|c| c != ',' && c != ']'), tag("]")),
-        );
-        map(p, |(a, b): (&str, &str)| (a.trim(), b.trim()))(i)
+    fn formula<'a>(i: &'a str, genus: &str) -> IResult<&'a str, Vec<PlantName>> {
+        verify(
+            separated_list1(tag(" × "), |i| parent(i, genus)),
+            |p: &[PlantName]| p.len() > 1,
+        )(i)
+    }
+
+    // Parse a parent in a hybrid formula. It is either a nested formula, e.g., `(pavia × sylvatica)`, or a name made up of
+    // an optional genus, the epithet and any infraspecific ranks, e.g., `glauca var. dasycephala`. Parents without a genus
+    // are in the genus of the hybrid.
+    fn parent<'a>(i: &'a str, genus: &str) -> IResult<&'a str, PlantName> {
+        let nested = delimited(tag("("), consumed(|i| formula(i, genus)), tag(")"))(i);
+        if let Ok((i, (raw, parents))) = nested {
+            return Ok((
+                i,
+                PlantName {
+                    genus: genus.to_string(),
+                    specific: raw.to_string(),
+                    species_type: SpeciesType::Hybrid,
+                    formula: Some(raw.to_string()),
+                    parents,
+                    ..Default::default()
+                },
+            ));
+        }
+
+        let (i, parent_genus) = opt(terminated(
+            verify(formula_epithet, |g: &str| g.starts_with(char::is_uppercase)),
+            tag(" "),
+        ))(i)?;
+        let (i, notho) = is_hybrid(i)?;
+        let (i, specific) = formula_epithet(i)?;
+        let (i, rank_chain) = many0(pair(rank_marker, formula_epithet))(i)?;
+
+        let mut name = PlantName {
+            genus: parent_genus.unwrap_or(genus).to_string(),
+            specific: specific.to_string(),
+            nothospecies: !notho.is_empty(),
+            ..Default::default()
+        };
+        for (m, epithet) in rank_chain {
+            if name.species_type != SpeciesType::Species {
+                name.ranks.push(InfraRank {
+                    rank: name.species_type,
+                    epithet: name.sspvar.take().unwrap_or_default(),
+                    author: None,
+                });
+            }
+            name.species_type = rank_type(m);
+            name.sspvar = Some(epithet.to_string());
+        }
+        if name.nothospecies && name.species_type == SpeciesType::Species {
+            name.species_type = SpeciesType::Hybrid;
+        }
+        Ok((i, name))
+    }
+
+    // Parse a nomenclatural status, e.g., `, nom. illeg.`
+    fn status(i: &str) -> IResult<&str, NomStatus> {
+        let (i, _) = tag(STATUS_START)(i)?;
+        for s in NomStatus::iter() {
+            let r: IResult<&str, &str> = tag(s.to_string().as_str())(i);
+            if let Ok((i, _)) = r {
+                return Ok((i, s));
+            }
+        }
+        Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Tag,
+        )))
+    }
+
+    // The position of the first nomenclatural status in a name, if there is one
+    pub fn find_status(name: &str) -> Option<usize> {
+        name.char_indices()
+            .map(|(n, _)| n)
+            .find(|&n| status(&name[n..]).is_ok())
     }
 
     fn sspvar(i: &str) -> IResult<&str, Option<&str>> {
-        opt(terminated(is_not(" "), multispace1))(i)
+        opt(terminated(
+            is_not(" ,["),
+            alt((
+                peek(tag(HYBRID_START)),
+                peek(tag(STATUS_START)),
+                peek(rank_marker),
+                multispace1,
+                eof,
+            )),
+        ))(i)
     }
 
-    fn modifier(i: &str) -> IResult<&str, &str> {
+    // An epithet after ` f. `. The same abbreviation is used for filius in authors, e.g., `Hook. f. ex Hemsl.` or
+    // `Michx. f. var. glaucum`, so it is only a forma if an epithet follows.
+    fn form_epithet(i: &str) -> IResult<&str, &str> {
+        verify(is_not(" ,["), |e: &str| {
+            e.starts_with(|c: char| c.is_lowercase())
+                && !e.contains('.')
+                && !["ex", "in", "et", "non", "sensu"].contains(&e)
+        })(i)
+    }
+
+    fn rank_marker(i: &str) -> IResult<&str, &str> {
         alt((
             tag(VARIETY),
             tag(SUBSPECIES),
-            tag(ORTH_VAR),
-            // preserve the opening [ to make later parsing easier
+            tag(SUBVARIETY),
+            tag(SUBFORM),
+            terminated(tag(FORM), peek(form_epithet)),
+        ))(i)
+    }
+
+    fn rank_type(marker: &str) -> SpeciesType {
+        match marker {
+            VARIETY => SpeciesType::Variety,
+            SUBSPECIES => SpeciesType::Subspecies,
+            SUBVARIETY => SpeciesType::Subvariety,
+            FORM => SpeciesType::Form,
+            SUBFORM => SpeciesType::Subform,
+            _ => SpeciesType::Species,
+        }
+    }
+
+    fn modifier(i: &str) -> IResult<&str, &str> {
+        alt((
+            rank_marker,
+            // preserve the opening [ and the status to make later parsing easier
+            peek(tag(HYBRID_START)),
+            peek(recognize(status)),
+            eof,
+        ))(i)
+    }
+
+    fn chars_to_string(chars: Vec<char>) -> String {
+        chars.into_iter().fold(String::new(), |mut acc, c| {
+            acc.push(c);
+            acc
+        })
+    }
+
+    // the end of an author, which is the start of whatever comes next in the name
+    fn author_end(i: &str) -> IResult<&str, &str> {
+        alt((
+            peek(rank_marker),
             peek(tag(HYBRID_START)),
+            peek(recognize(status)),
             eof,
         ))(i)
     }
 
     fn second_author(i: &str) -> IResult<&str, Option<&str>> {
-        opt(terminated(is_not("\n"), eof))(i)
+        let (rest, author) = recognize(many_till(anychar, author_end))(i)?;
+        Ok((rest, Some(author).filter(|a| !a.is_empty())))
     }
 
     fn parse_author(i: &str) -> IResult<&str, String> {
-        let parser = many_till(
-            anychar,
-            alt((
-                peek(tag(VARIETY)),
-                peek(tag(SUBSPECIES)),
-                peek(tag(ORTH_VAR)),
-                peek(tag(HYBRID_START)),
-                eof,
-            )),
-        );
+        let parser = many_till(anychar, author_end);
         // this seems very hacky but I can not find a nom parser that will just parse chars as string until some condition,
         // so we end up with a Vec<char> since `anychar` only operates at the char level.
-        map(parser, |r| {
-            r.0.into_iter()
-                .fold(String::new(), |mut acc, c| {
-                    acc.push(c);
-                    acc
-                })
-                .to_string()
-        })(i)
+        map(parser, |r| chars_to_string(r.0))(i)
+    }
+
+    // the start of something that qualifies the authors rather than being one of them, e.g., `, non L.`, `auct.`,
+    // `sensu Small`, `(pro sp.)` or `, ined.`
+    fn qualifier_start(i: &str) -> IResult<&str, &str> {
+        alt((
+            tag(", non "),
+            tag(", ined."),
+            tag(", database artifact"),
+            tag(" non "),
+            tag(" (pro"),
+            tag(" auct."),
+            tag(" sensu "),
+        ))(i)
+    }
+
+    fn team_separator(i: &str) -> IResult<&str, &str> {
+        alt((tag(", & "), tag(" & "), tag(", "), tag(" et ")))(i)
+    }
+
+    fn author_separator(i: &str) -> IResult<&str, &str> {
+        alt((
+            tag(" & "),
+            tag(", "),
+            tag(" et "),
+            tag(" ex "),
+            tag(" in "),
+            tag(" : "),
+            tag(": "),
+            tag("("),
+            tag(")"),
+        ))(i)
+    }
+
+    // a single author, e.g., `Michx. f.` or `Burtt Davy`
+    fn author_name(i: &str) -> IResult<&str, String> {
+        map(
+            recognize(many1(preceded(not(author_separator), anychar))),
+            |a: &str| a.to_string(),
+        )(i)
+    }
+
+    // the authors of a single publication, e.g., `Britton, Sterns & Poggenb.`
+    fn author_team(i: &str) -> IResult<&str, Vec<String>> {
+        separated_list1(team_separator, author_name)(i)
+    }
+
+    fn authors(i: &str) -> IResult<&str, Authors> {
+        let (i, first) = author_team(i)?;
+        let (i, ex) = opt(preceded(tag(" ex "), author_team))(i)?;
+        let (i, in_authors) = opt(preceded(tag(" in "), author_team))(i)?;
+        let (i, sanctioning) = opt(preceded(alt((tag(" : "), tag(": "))), author_team))(i)?;
+        let (authors, ex_authors) = match ex {
+            Some(ex) => (ex, first),
+            None => (first, vec![]),
+        };
+        Ok((
+            i,
+            Authors {
+                authors,
+                ex_authors,
+                in_authors: in_authors.unwrap_or_default(),
+                sanctioning: sanctioning.unwrap_or_default(),
+            },
+        ))
+    }
+
+    fn authorship(i: &str) -> IResult<&str, Authorship> {
+        let (i, basionym) = opt(delimited(tag("("), authors, tag(")")))(i)?;
+        let (i, combining) = if basionym.is_some() {
+            opt(preceded(space1, authors))(i)?
+        } else {
+            opt(authors)(i)?
+        };
+        Ok((
+            i,
+            Authorship {
+                basionym,
+                combining,
+                qualifier: None,
+            },
+        ))
+    }
+
+    // Split an author string into its parts. This can not fail, anything that is not understood is put in the qualifier.
+    pub fn parse_authorship(raw: &str) -> Authorship {
+        let raw = raw.trim();
+        // misapplied names have no authors of their own, e.g., `auct. non Benth.` or `sensu Greene, non Nutt.`
+        if ["auct.", "sensu ", "non "]
+            .iter()
+            .any(|q| raw.starts_with(q))
+        {
+            return Authorship {
+                qualifier: Some(raw.to_string()),
+                ..Default::default()
+            };
+        }
+        let (head, qualifier) = match recognize(many_till(anychar, peek(qualifier_start)))(raw) {
+            Ok((rest, head)) => (head, Some(rest.trim_start_matches(',').trim())),
+            Err(_) => (raw, None),
+        };
+        match all_consuming(authorship)(head) {
+            Ok((_, a)) => Authorship {
+                qualifier: qualifier.map(|q| q.to_string()),
+                ..a
+            },
+            Err(_) => Authorship {
+                qualifier: Some(raw.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    // Parse a hybrid that only has a formula rather than a name of its own, e.g., `Pinus rigida × taeda [unnamed hybrid]`
+    fn parse_formula_name(i: &str) -> IResult<&str, PlantName> {
+        let (i, genus) = not_whitespace(i)?;
+        let (i, _) = space1(i)?;
+        let (i, (raw, parents)) = consumed(|i| formula(i, genus))(i)?;
+        let (i, note) = opt(brackets)(i)?;
+        let (i, _) = eof(i)?;
+
+        Ok((
+            i,
+            PlantName {
+                genus: genus.to_string(),
+                specific: raw.to_string(),
+                species_type: SpeciesType::Hybrid,
+                formula: Some(note.unwrap_or(raw).to_string()),
+                parents,
+                ..Default::default()
+            },
+        ))
     }
 
     fn parse_name_internal(i: &str) -> IResult<&str, PlantName> {
+        let (i, nothogenus) = is_hybrid(i)?;
         let (i, genus) = not_whitespace(i)?;
-        let (i, _) = nom::character::complete::space1(i)?;
-        let (i, _) = is_hybrid(i)?;
+        let (i, _) = space1(i)?;
+        let (i, nothospecies) = is_hybrid(i)?;
         let (i, specific) = not_whitespace(i)?;
-        let (i, _) = nom::character::complete::space1(i)?;
-        let (i, author) = parse_author(i)?;
-        let (i, species_type, hybrid, sspvar) = match modifier(i) {
-            Ok((i, VARIETY)) => {
-                let (ii, sv) = sspvar(i)?;
-                (ii, SpeciesType::Variety, None, sv.map(|x| x.to_string()))
+        // names without authors go straight on to the rank, e.g., `Acer saccharum var. nigrum`
+        let (i, author) = alt((
+            value(String::new(), author_end),
+            preceded(space1, parse_author),
+        ))(i)?;
+        let (i, m) = modifier(i)?;
+        let (i, mut species_type, mut formula_text, mut epithet) = match m {
+            HYBRID_START => {
+                let (ii, raw) = brackets(i)?;
+                (ii, SpeciesType::Species, Some(raw), None)
             }
-            Ok((i, SUBSPECIES)) => {
+            VARIETY | SUBSPECIES | SUBVARIETY | FORM | SUBFORM => {
                 let (ii, sv) = sspvar(i)?;
-                (ii, SpeciesType::Subspecies, None, sv.map(|x| x.to_string()))
+                (ii, rank_type(m), None, sv.map(|x| x.to_string()))
             }
-            Ok((i, HYBRID_START)) => {
-                let (ii, (a, b)) = hybrid(i)?;
-                (
-                    ii,
-                    SpeciesType::Hybrid,
-                    Some((a.to_string(), b.to_string())),
-                    None,
-                )
-            }
-            Ok((i, ORTH_VAR)) => (i, SpeciesType::OrthVar, None, None),
-            Ok((i, _)) => (i, SpeciesType::Species, None, None),
-            // for now we are going to ignore all of the other possibilies which are all weird esoteric taxonomy naming things
-            Err(_) => ("", SpeciesType::Other, None, None),
+            _ => (i, SpeciesType::Species, None, None),
         };
-        let (i, second_author) = second_author(i)?;
+        let (mut i, mut rank_author) = second_author(i)?;
+
+        // each further rank makes the one before it a parent rank, e.g., `ssp. americana var. expansa`
+        let mut ranks = Vec::new();
+        while let Ok((ii, m)) = rank_marker(i) {
+            ranks.push(InfraRank {
+                rank: species_type,
+                epithet: epithet.unwrap_or_default(),
+                author: rank_author.map(|a| a.to_string()),
+            });
+            let (ii, sv) = sspvar(ii)?;
+            let (ii, a) = second_author(ii)?;
+            species_type = rank_type(m);
+            epithet = sv.map(|x| x.to_string());
+            rank_author = a;
+            i = ii;
+        }
+        let (i, status) = opt(status)(i)?;
+
+        // the formula can come after the infraspecific ranks or the status, e.g.,
+        // `Mentha ×verticillata L., nom. inq. [aquatica × arvensis]`
+        let (i, late_formula) = opt(brackets)(i)?;
+        if late_formula.is_some() {
+            formula_text = late_formula;
+        }
+        // the brackets do not always hold a formula, e.g., `[excluded]`, in which case there are no parents
+        let parents = formula_text
+            .and_then(|f| all_consuming(|i| formula(i, genus))(f).ok())
+            .map(|(_, p)| p)
+            .unwrap_or_default();
+        if species_type == SpeciesType::Species && (!nothospecies.is_empty() || !parents.is_empty())
+        {
+            species_type = SpeciesType::Hybrid;
+        }
+
+        let mut author = author;
+        let mut second_author = rank_author.map(|a| a.to_string());
+        // anything left after the status qualifies the authors, e.g., `, non Quercus ×ashei Trel.`, so put it back where
+        // it would have been without the status
+        if !i.is_empty() {
+            match second_author.as_mut() {
+                Some(a) => a.push_str(i),
+                None => author.push_str(i),
+            }
+        }
 
         Ok((
-            i,
+            "",
             PlantName {
                 genus: genus.to_string(),
                 specific: specific.to_string(),
                 species_type,
-                sspvar,
-                hybrid,
-                author: Some(author.to_string()),
-                second_author: second_author.map(|a| a.to_string()),
+                sspvar: epithet,
+                author: Some(author).filter(|a| !a.is_empty()),
+                second_author,
+                status,
+                ranks,
+                nothogenus: !nothogenus.is_empty(),
+                nothospecies: !nothospecies.is_empty(),
+                formula: formula_text.map(|f| f.to_string()),
+                parents,
             },
         ))
     }
 
+    // Replace the ASCII hybrid signs in a name that was written by hand with ×, joined to the epithet that it marks,
+    // e.g., `Quercus x leana` becomes `Quercus ×leana` and `x Agropogon lutosus` becomes `×Agropogon lutosus`. After the
+    // specific it separates the parents in a formula, e.g., `Pinus rigida x taeda`.
+    fn normalise_hybrid_signs(name: &str) -> String {
+        let mut words: Vec<String> = Vec::new();
+        let mut marks_next = false;
+        for w in name.split_whitespace() {
+            if ["x", "X", "×"].contains(&w) {
+                if words.len() < 2 {
+                    marks_next = true;
+                } else {
+                    words.push("×".to_string());
+                }
+            } else if marks_next {
+                words.push(format!("×{}", w));
+                marks_next = false;
+            } else {
+                words.push(w.to_string());
+            }
+        }
+        words.join(" ")
+    }
+
+    pub fn parse_name_tolerant(i: &str) -> Result<PlantName, nom::error::Error<String>> {
+        let name = normalise_hybrid_signs(i);
+        let genus_only: IResult<&str, (&str, &str)> = all_consuming(pair(
+            is_hybrid,
+            terminated(
+                not_whitespace,
+                opt(pair(space1, alt((tag("spp."), tag("sp."))))),
+            ),
+        ))(&name);
+        match genus_only {
+            Ok((_, (notho, genus))) => Ok(PlantName {
+                genus: genus.to_string(),
+                nothogenus: !notho.is_empty(),
+                ..Default::default()
+            }),
+            Err(_) => parse_name(&name),
+        }
+    }
+
     pub fn parse_name(i: &str) -> Result<PlantName, nom::error::Error<String>> {
-        match parse_name_internal(i).finish() {
+        match alt((parse_formula_name, parse_name_internal))(i).finish() {
             Ok((_, n)) => Ok(n),
             Err(e) => Err(nom::error::Error::new(e.input.to_string(), e.code)),
         }
@@ -240,30 +850,112 @@ pub(self) mod parsers {
             assert_eq!(is_hybrid("alba"), Ok(("alba", "")));
         }
 
+        fn sp(genus: &str, specific: &str) -> PlantName {
+            PlantName {
+                genus: genus.to_string(),
+                specific: specific.to_string(),
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_brackets() {
+            assert_eq!(
+                brackets(" [alba × michauxii]"),
+                Ok(("", "alba × michauxii"))
+            );
+            assert_eq!(brackets(" [unnamed hybrid]"), Ok(("", "unnamed hybrid")));
+            assert!(brackets("Foo bar").is_err());
+            assert!(brackets(" []").is_err());
+        }
+
         #[test]
-        fn test_hybrid() {
+        fn test_formula() {
             assert_eq!(
-                hybrid(" [alba × michauxii]"),
-                Ok(("", ("alba", "michauxii")))
+                formula("alba × michauxii", "Quercus"),
+                Ok(("", vec![sp("Quercus", "alba"), sp("Quercus", "michauxii")]))
             );
             assert_eq!(
-                hybrid(" [Agrostis stolonifera × Polypogon monospeliensis]"),
-                Ok(("", ("Agrostis stolonifera", "Polypogon monospeliensis")))
+                formula(
+                    "Agrostis stolonifera × Polypogon monospeliensis",
+                    "×Agropogon"
+                ),
+                Ok((
+                    "",
+                    vec![
+                        sp("Agrostis", "stolonifera"),
+                        sp("Polypogon", "monospeliensis")
+                    ]
+                ))
             );
             assert_eq!(
-                hybrid(" [ericoides × novae-angliae]"),
-                Ok(("", ("ericoides", "novae-angliae")))
+                formula("brevicaulis × fulva × hexagona", "Iris"),
+                Ok((
+                    "",
+                    vec![
+                        sp("Iris", "brevicaulis"),
+                        sp("Iris", "fulva"),
+                        sp("Iris", "hexagona")
+                    ]
+                ))
             );
             assert_eq!(
-                hybrid("Foo bar"),
-                Err(nom::Err::Error(nom::error::Error {
-                    input: "Foo bar",
-                    code: nom::error::ErrorKind::Tag,
-                }))
+                formula("×ebenoides × platyneuron", "Asplenium"),
+                Ok((
+                    "",
+                    vec![
+                        PlantName {
+                            species_type: SpeciesType::Hybrid,
+                            nothospecies: true,
+                            ..sp("Asplenium", "ebenoides")
+                        },
+                        sp("Asplenium", "platyneuron")
+                    ]
+                ))
             );
-            assert!(hybrid(" []").is_err());
-            assert!(hybrid(" [alba michauxii]").is_err());
-            assert!(hybrid(" [alba x michauxii]").is_err());
+            assert_eq!(
+                formula(
+                    "tridentata ssp. vaseyana × glauca var. dasycephala",
+                    "Artemisia"
+                ),
+                Ok((
+                    "",
+                    vec![
+                        PlantName {
+                            species_type: SpeciesType::Subspecies,
+                            sspvar: Some("vaseyana".to_string()),
+                            ..sp("Artemisia", "tridentata")
+                        },
+                        PlantName {
+                            species_type: SpeciesType::Variety,
+                            sspvar: Some("dasycephala".to_string()),
+                            ..sp("Artemisia", "glauca")
+                        }
+                    ]
+                ))
+            );
+            assert_eq!(
+                formula("flava × (pavia × sylvatica)", "Aesculus"),
+                Ok((
+                    "",
+                    vec![
+                        sp("Aesculus", "flava"),
+                        PlantName {
+                            species_type: SpeciesType::Hybrid,
+                            formula: Some("pavia × sylvatica".to_string()),
+                            parents: vec![sp("Aesculus", "pavia"), sp("Aesculus", "sylvatica")],
+                            ..sp("Aesculus", "pavia × sylvatica")
+                        }
+                    ]
+                ))
+            );
+            assert!(formula("alba", "Quercus").is_err());
+            assert!(all_consuming(|i| formula(i, "Quercus"))("alba michauxii").is_err());
+            assert!(all_consuming(|i| formula(i, "Quercus"))("alba x michauxii").is_err());
+            assert!(all_consuming(|i| formula(i, "Arnica"))(
+                "amplexicaulis or mollis × cordifolia"
+            )
+            .is_err());
         }
 
         #[test]
@@ -283,7 +975,11 @@ pub(self) mod parsers {
             );
             assert_eq!(
                 parse_author("L., orth. var."),
-                Ok((ORTH_VAR, "L.".to_string()))
+                Ok((", orth. var.", "L.".to_string()))
+            );
+            assert_eq!(
+                parse_author("L., non Rehder, nom. illeg."),
+                Ok((", nom. illeg.", "L., non Rehder".to_string()))
             );
             assert_eq!(parse_author("Foo bar"), Ok(("", "Foo bar".to_string())));
             assert_eq!(
@@ -296,14 +992,17 @@ pub(self) mod parsers {
             );
             assert_eq!(
                 parse_author("Foo bar, orth. var."),
-                Ok((ORTH_VAR, "Foo bar".to_string()))
+                Ok((", orth. var.", "Foo bar".to_string()))
             );
         }
 
         #[test]
         fn test_modifier() {
             assert_eq!(modifier(""), Ok(("", "")));
-            assert_eq!(modifier(ORTH_VAR), Ok(("", ORTH_VAR)));
+            assert_eq!(
+                modifier(", orth. var."),
+                Ok((", orth. var.", ", orth. var."))
+            );
             assert_eq!(modifier(" ssp. foo"), Ok(("foo", SUBSPECIES)));
             assert_eq!(modifier(" var. foo"), Ok(("foo", VARIETY)));
             assert_eq!(
@@ -312,12 +1011,206 @@ pub(self) mod parsers {
             );
         }
 
+        #[test]
+        fn test_status() {
+            assert_eq!(status(", nom. illeg."), Ok(("", NomStatus::NomIlleg)));
+            assert_eq!(
+                status(", nom. utique rej."),
+                Ok(("", NomStatus::NomUtiqueRej))
+            );
+            assert_eq!(
+                status(", nom. inq. [aquatica × arvensis]"),
+                Ok((" [aquatica × arvensis]", NomStatus::NomInq))
+            );
+            assert!(status(", non Rehder").is_err());
+            assert!(status("nom. illeg.").is_err());
+        }
+
+        #[test]
+        fn test_rank_marker() {
+            assert_eq!(rank_marker(" subvar. foo"), Ok(("foo", SUBVARIETY)));
+            assert_eq!(rank_marker(" f. johnsonii"), Ok(("johnsonii", FORM)));
+            // filius
+            assert!(rank_marker(" f. ex Hemsl.").is_err());
+            assert!(rank_marker(" f. var. glaucum").is_err());
+            assert!(rank_marker(" f. & Wilson").is_err());
+            assert!(rank_marker(" f.").is_err());
+        }
+
         #[test]
         fn test_sspvar() {
             assert_eq!(sspvar("foo "), Ok(("", Some("foo"))));
             assert_eq!(sspvar("foo bar"), Ok(("bar", Some("foo"))));
             assert_eq!(sspvar("foo bar baz"), Ok(("bar baz", Some("foo"))));
             assert_eq!(sspvar(""), Ok(("", None)));
+            assert_eq!(sspvar("foo"), Ok(("", Some("foo"))));
+            assert_eq!(
+                sspvar("foo, nom. illeg."),
+                Ok((", nom. illeg.", Some("foo")))
+            );
+        }
+
+        fn team(authors: &[&str]) -> Authors {
+            Authors {
+                authors: authors.iter().map(|a| a.to_string()).collect(),
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_parse_authorship() {
+            assert_eq!(
+                parse_authorship("(J.F. Gmel.) Steud."),
+                Authorship {
+                    basionym: Some(team(&["J.F. Gmel."])),
+                    combining: Some(team(&["Steud."])),
+                    qualifier: None,
+                }
+            );
+            assert_eq!(
+                parse_authorship("Trel. ex Palmer (pro sp.)"),
+                Authorship {
+                    basionym: None,
+                    combining: Some(Authors {
+                        ex_authors: vec!["Trel.".to_string()],
+                        ..team(&["Palmer"])
+                    }),
+                    qualifier: Some("(pro sp.)".to_string()),
+                }
+            );
+            assert_eq!(
+                parse_authorship("(Douglas ex D. Don) Lindl."),
+                Authorship {
+                    basionym: Some(Authors {
+                        ex_authors: vec!["Douglas".to_string()],
+                        ..team(&["D. Don"])
+                    }),
+                    combining: Some(team(&["Lindl."])),
+                    qualifier: None,
+                }
+            );
+            assert_eq!(
+                parse_authorship("(Walter) Britton, Sterns & Poggenb."),
+                Authorship {
+                    basionym: Some(team(&["Walter"])),
+                    combining: Some(team(&["Britton", "Sterns", "Poggenb."])),
+                    qualifier: None,
+                }
+            );
+            assert_eq!(
+                parse_authorship("Torr. in Emory"),
+                Authorship {
+                    combining: Some(Authors {
+                        in_authors: vec!["Emory".to_string()],
+                        ..team(&["Torr."])
+                    }),
+                    ..Default::default()
+                }
+            );
+            assert_eq!(
+                parse_authorship("(Pers. : Fr.) Hook. f. ex Hemsl. in Bentham"),
+                Authorship {
+                    basionym: Some(Authors {
+                        sanctioning: vec!["Fr.".to_string()],
+                        ..team(&["Pers."])
+                    }),
+                    combining: Some(Authors {
+                        ex_authors: vec!["Hook. f.".to_string()],
+                        in_authors: vec!["Bentham".to_string()],
+                        ..team(&["Hemsl."])
+                    }),
+                    qualifier: None,
+                }
+            );
+            assert_eq!(
+                parse_authorship("Engelm. ex Torr., non Regel"),
+                Authorship {
+                    basionym: None,
+                    combining: Some(Authors {
+                        ex_authors: vec!["Engelm.".to_string()],
+                        ..team(&["Torr."])
+                    }),
+                    qualifier: Some("non Regel".to_string()),
+                }
+            );
+            assert_eq!(
+                parse_authorship("auct. non (L. f.) Vahl"),
+                Authorship {
+                    qualifier: Some("auct. non (L. f.) Vahl".to_string()),
+                    ..Default::default()
+                }
+            );
+            // not authors at all so it is kept as it is
+            assert_eq!(
+                parse_authorship("(Pollard & (Maxon) Millsp."),
+                Authorship {
+                    qualifier: Some("(Pollard & (Maxon) Millsp.".to_string()),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn test_authorship_display() {
+            for a in [
+                "L.",
+                "(J.F. Gmel.) Steud.",
+                "Trel. ex Palmer (pro sp.)",
+                "(Douglas ex D. Don) Lindl.",
+                "(Walter) Britton, Sterns & Poggenb.",
+                "(Pers. : Fr.) Hook. f. ex Hemsl. in Bentham",
+                "Engelm. ex Torr., non Regel",
+                "(A. Gray) Kartesz, ined.",
+                "Aiton sensu Small",
+                "auct. non Benth.",
+            ] {
+                assert_eq!(parse_authorship(a).to_string(), a);
+            }
+            assert_eq!(
+                parse_authorship("Triplett, Weakley, & L.G. Clark").to_string(),
+                "Triplett, Weakley & L.G. Clark"
+            );
+        }
+
+        #[test]
+        fn test_same_authors() {
+            let a = parse_authorship("(Douglas ex D. Don) Lindl.");
+            assert!(a.same_authors(&parse_authorship("(D. Don) Lindl.")));
+            assert!(a.same_authors(&parse_authorship("(Douglas ex D. Don) Lindl. (pro sp.)")));
+            assert!(!a.same_authors(&parse_authorship("(D. Don) Rydb.")));
+            assert!(!a.same_authors(&parse_authorship("Lindl.")));
+
+            let n = parse_name("Pinus contorta Douglas ex Loudon var. murrayana (Balf.) Engelm.")
+                .unwrap();
+            assert!(n.same_authors(
+                &parse_name("Pinus contorta Loudon var. murrayana (Balf.) Engelm.").unwrap()
+            ));
+            assert!(!n.same_authors(
+                &parse_name("Pinus contorta Loudon var. murrayana (Grev. & Balf.) Engelm.")
+                    .unwrap()
+            ));
+            assert!(!n.same_authors(&parse_name("Pinus contorta Loudon var. murrayana").unwrap()));
+        }
+
+        #[test]
+        fn test_plant_name_authorship() {
+            let n = parse_name("Pinus contorta Douglas ex Loudon var. murrayana (Balf.) Engelm.")
+                .unwrap();
+            assert_eq!(
+                n.authorship().unwrap().combining,
+                Some(Authors {
+                    ex_authors: vec!["Douglas".to_string()],
+                    ..team(&["Loudon"])
+                })
+            );
+            assert_eq!(
+                n.second_authorship().unwrap().basionym,
+                Some(team(&["Balf."]))
+            );
+            assert_eq!(
+                parse_name("Quercus alba L.").unwrap().second_authorship(),
+                None
+            );
         }
 
         #[test]
@@ -327,6 +1220,10 @@ pub(self) mod parsers {
                 Ok(("", Some("A.L. Pickens & M.C. Pickens")))
             );
             assert_eq!(second_author(""), Ok(("", None)));
+            assert_eq!(
+                second_author("Nees, nom. illeg."),
+                Ok((", nom. illeg.", Some("Nees")))
+            );
         }
 
         #[test]
@@ -363,9 +1260,9 @@ pub(self) mod parsers {
                     specific: "caroliniensis".to_string(),
                     species_type: SpeciesType::Subspecies,
                     sspvar: Some("ciliosa".to_string()),
-                    hybrid: None,
                     author: Some("(J.F. Gmel.) Steud.".to_string()),
                     second_author: Some("(Pursh) R.W. Long".to_string()),
+                    ..Default::default()
                 })
             );
 
@@ -376,9 +1273,12 @@ pub(self) mod parsers {
                     specific: "beadlei".to_string(),
                     species_type: SpeciesType::Hybrid,
                     sspvar: None,
-                    hybrid: Some(("alba".to_string(), "michauxii".to_string())),
                     author: Some("Trel. ex Palmer".to_string()),
                     second_author: None,
+                    nothospecies: true,
+                    formula: Some("alba × michauxii".to_string()),
+                    parents: vec![sp("Quercus", "alba"), sp("Quercus", "michauxii")],
+                    ..Default::default()
                 })
             );
 
@@ -387,13 +1287,365 @@ pub(self) mod parsers {
                 Ok(PlantName {
                     genus: "Acaena".to_string(),
                     specific: "novae-zelandica".to_string(),
-                    species_type: SpeciesType::OrthVar,
+                    species_type: SpeciesType::Species,
                     sspvar: None,
-                    hybrid: None,
                     author: Some("Kirk".to_string()),
                     second_author: None,
+                    status: Some(NomStatus::OrthVar),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Adhatoda furcata (Jacq.) Nees var. terminalis Nees, nom. illeg."),
+                Ok(PlantName {
+                    genus: "Adhatoda".to_string(),
+                    specific: "furcata".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("terminalis".to_string()),
+                    author: Some("(Jacq.) Nees".to_string()),
+                    second_author: Some("Nees".to_string()),
+                    status: Some(NomStatus::NomIlleg),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Mentha ×verticillata L., nom. inq. [aquatica × arvensis]"),
+                Ok(PlantName {
+                    genus: "Mentha".to_string(),
+                    specific: "verticillata".to_string(),
+                    species_type: SpeciesType::Hybrid,
+                    sspvar: None,
+                    author: Some("L.".to_string()),
+                    second_author: None,
+                    status: Some(NomStatus::NomInq),
+                    nothospecies: true,
+                    formula: Some("aquatica × arvensis".to_string()),
+                    parents: vec![sp("Mentha", "aquatica"), sp("Mentha", "arvensis")],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Abies grandis (Douglas ex D. Don) Lindl. f. johnsonii O.V. Matthews"),
+                Ok(PlantName {
+                    genus: "Abies".to_string(),
+                    specific: "grandis".to_string(),
+                    species_type: SpeciesType::Form,
+                    sspvar: Some("johnsonii".to_string()),
+                    author: Some("(Douglas ex D. Don) Lindl.".to_string()),
+                    second_author: Some("O.V. Matthews".to_string()),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Acer nigrum Michx. f. var. glaucum (F. Schmidt) Fosberg"),
+                Ok(PlantName {
+                    genus: "Acer".to_string(),
+                    specific: "nigrum".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("glaucum".to_string()),
+                    author: Some("Michx. f.".to_string()),
+                    second_author: Some("(F. Schmidt) Fosberg".to_string()),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Anatherum virginicum (L.) Spreng. subvar. mohrii (Hack.) Roberty"),
+                Ok(PlantName {
+                    genus: "Anatherum".to_string(),
+                    specific: "virginicum".to_string(),
+                    species_type: SpeciesType::Subvariety,
+                    sspvar: Some("mohrii".to_string()),
+                    author: Some("(L.) Spreng.".to_string()),
+                    second_author: Some("(Hack.) Roberty".to_string()),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Quercus alba L. var. alba"),
+                Ok(PlantName {
+                    genus: "Quercus".to_string(),
+                    specific: "alba".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("alba".to_string()),
+                    author: Some("L.".to_string()),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name(
+                    "Agastache pallidiflora (A. Heller) Rydb. ssp. neomexicana (Briq.) Lint & Epling var. havardii (A. Gray) R.W. Sanders"
+                ),
+                Ok(PlantName {
+                    genus: "Agastache".to_string(),
+                    specific: "pallidiflora".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("havardii".to_string()),
+                    author: Some("(A. Heller) Rydb.".to_string()),
+                    second_author: Some("(A. Gray) R.W. Sanders".to_string()),
+                    ranks: vec![InfraRank {
+                        rank: SpeciesType::Subspecies,
+                        epithet: "neomexicana".to_string(),
+                        author: Some("(Briq.) Lint & Epling".to_string()),
+                    }],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Agave americana L. ssp. americana var. americana"),
+                Ok(PlantName {
+                    genus: "Agave".to_string(),
+                    specific: "americana".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("americana".to_string()),
+                    author: Some("L.".to_string()),
+                    ranks: vec![InfraRank {
+                        rank: SpeciesType::Subspecies,
+                        epithet: "americana".to_string(),
+                        author: None,
+                    }],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name(
+                    "×Achnella caduca (Beal) Barkworth [Achnatherum hymenoides × Nassella viridula]"
+                ),
+                Ok(PlantName {
+                    genus: "Achnella".to_string(),
+                    specific: "caduca".to_string(),
+                    species_type: SpeciesType::Hybrid,
+                    author: Some("(Beal) Barkworth".to_string()),
+                    nothogenus: true,
+                    formula: Some("Achnatherum hymenoides × Nassella viridula".to_string()),
+                    parents: vec![
+                        sp("Achnatherum", "hymenoides"),
+                        sp("Nassella", "viridula")
+                    ],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("×Agroelymus mossii Lepage"),
+                Ok(PlantName {
+                    genus: "Agroelymus".to_string(),
+                    specific: "mossii".to_string(),
+                    author: Some("Lepage".to_string()),
+                    nothogenus: true,
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Pinus rigida × taeda [unnamed hybrid]"),
+                Ok(PlantName {
+                    genus: "Pinus".to_string(),
+                    specific: "rigida × taeda".to_string(),
+                    species_type: SpeciesType::Hybrid,
+                    formula: Some("unnamed hybrid".to_string()),
+                    parents: vec![sp("Pinus", "rigida"), sp("Pinus", "taeda")],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name(
+                    "Cypripedium ×andrewsii A.M. Fuller var. favillianum (J.T. Curtis) B. Boivin (pro nm.) [candidum × parviflorum var. pubescens]"
+                ),
+                Ok(PlantName {
+                    genus: "Cypripedium".to_string(),
+                    specific: "andrewsii".to_string(),
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("favillianum".to_string()),
+                    author: Some("A.M. Fuller".to_string()),
+                    second_author: Some("(J.T. Curtis) B. Boivin (pro nm.)".to_string()),
+                    nothospecies: true,
+                    formula: Some("candidum × parviflorum var. pubescens".to_string()),
+                    parents: vec![
+                        sp("Cypripedium", "candidum"),
+                        PlantName {
+                            species_type: SpeciesType::Variety,
+                            sspvar: Some("pubescens".to_string()),
+                            ..sp("Cypripedium", "parviflorum")
+                        }
+                    ],
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Arnica ×diversifolia Greene (pro sp.) [amplexicaulis or mollis × cordifolia or latifolia]"),
+                Ok(PlantName {
+                    genus: "Arnica".to_string(),
+                    specific: "diversifolia".to_string(),
+                    species_type: SpeciesType::Hybrid,
+                    author: Some("Greene (pro sp.)".to_string()),
+                    nothospecies: true,
+                    formula: Some("amplexicaulis or mollis × cordifolia or latifolia".to_string()),
+                    ..Default::default()
+                })
+            );
+
+            assert_eq!(
+                parse_name("Quercus ashei Sterrett, nom. illeg., non Quercus ×ashei Trel."),
+                Ok(PlantName {
+                    genus: "Quercus".to_string(),
+                    specific: "ashei".to_string(),
+                    author: Some("Sterrett, non Quercus ×ashei Trel.".to_string()),
+                    status: Some(NomStatus::NomIlleg),
+                    ..Default::default()
+                })
+            );
+        }
+
+        #[test]
+        fn test_parse_name_tolerant() {
+            assert_eq!(normalise_hybrid_signs("Quercus x leana"), "Quercus ×leana");
+            assert_eq!(
+                normalise_hybrid_signs("X Agropogon  lutosus"),
+                "×Agropogon lutosus"
+            );
+            assert_eq!(
+                normalise_hybrid_signs("Pinus rigida x taeda"),
+                "Pinus rigida × taeda"
+            );
+
+            assert_eq!(
+                parse_name_tolerant("Quercus x leana"),
+                Ok(PlantName {
+                    nothospecies: true,
+                    species_type: SpeciesType::Hybrid,
+                    ..sp("Quercus", "leana")
                 })
             );
+            assert_eq!(
+                parse_name_tolerant("Acer saccharum var. nigrum"),
+                Ok(PlantName {
+                    species_type: SpeciesType::Variety,
+                    sspvar: Some("nigrum".to_string()),
+                    ..sp("Acer", "saccharum")
+                })
+            );
+            assert_eq!(
+                parse_name_tolerant("x Agropogon lutosus"),
+                Ok(PlantName {
+                    nothogenus: true,
+                    ..sp("Agropogon", "lutosus")
+                })
+            );
+            assert_eq!(
+                parse_name_tolerant("Pinus rigida x taeda").map(|n| n.parents),
+                Ok(vec![sp("Pinus", "rigida"), sp("Pinus", "taeda")])
+            );
+            // authors are allowed but not needed
+            assert_eq!(
+                parse_name_tolerant("Quercus alba L.").map(|n| n.author),
+                Ok(Some("L.".to_string()))
+            );
+            assert_eq!(parse_name_tolerant("Quercus"), Ok(sp("Quercus", "")));
+            assert_eq!(parse_name_tolerant("Quercus spp."), Ok(sp("Quercus", "")));
+            assert!(parse_name_tolerant("").is_err());
+            // the USDA grammar does not accept the ASCII sign
+            assert_ne!(
+                parse_name("Quercus x leana"),
+                parse_name_tolerant("Quercus x leana")
+            );
+        }
+
+        #[test]
+        fn test_render() {
+            let cases = [
+                (
+                    "Quercus alba L.",
+                    "Quercus alba",
+                    "Quercus alba",
+                ),
+                (
+                    "Quercus ×leana Nutt. (pro sp.) [imbricaria × velutina]",
+                    "Quercus ×leana",
+                    "Quercus x leana",
+                ),
+                (
+                    "Acer saccharum Marshall var. nigrum (F. Michx.) Britton",
+                    "Acer saccharum var. nigrum",
+                    "Acer saccharum var. nigrum",
+                ),
+                (
+                    "Agave americana L. ssp. americana var. expansa (Jacobi) Gentry",
+                    "Agave americana ssp. americana var. expansa",
+                    "Agave americana var. expansa",
+                ),
+                (
+                    "×Achnella caduca (Beal) Barkworth [Achnatherum hymenoides × Nassella viridula]",
+                    "×Achnella caduca",
+                    "x Achnella caduca",
+                ),
+                (
+                    "Pinus rigida × taeda [unnamed hybrid]",
+                    "Pinus rigida × taeda",
+                    "Pinus rigida x taeda",
+                ),
+                (
+                    "Mentha ×verticillata L., nom. inq. [aquatica × arvensis]",
+                    "Mentha ×verticillata",
+                    "Mentha x verticillata",
+                ),
+            ];
+            for (raw, canonical, gallformers) in cases {
+                let name = parse_name(raw).unwrap();
+                assert_eq!(name.render(NameStyle::Full), raw);
+                assert_eq!(name.to_string(), raw);
+                assert_eq!(name.render(NameStyle::Canonical), canonical);
+                assert_eq!(name.render(NameStyle::Gallformers), gallformers);
+            }
+
+            // a status that USDA puts after the formula is moved in front of it
+            assert_eq!(
+                parse_name("Mentha ×verticillata L. [aquatica × arvensis], nom. inq.")
+                    .map(|n| n.to_string()),
+                Ok("Mentha ×verticillata L., nom. inq. [aquatica × arvensis]".to_string())
+            );
+        }
+
+        // every name in the USDA data that can be parsed renders to a name that parses back to the same thing
+        #[test]
+        fn test_round_trip_corpus() {
+            let data = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+            let mut names = std::collections::BTreeSet::new();
+            for csv in data.read_dir().unwrap() {
+                let mut rdr = csv::ReaderBuilder::new()
+                    .flexible(true)
+                    .from_path(csv.unwrap().path())
+                    .unwrap();
+                for r in rdr.byte_records().flatten() {
+                    if let Some(n) = r.get(2) {
+                        names.insert(String::from_utf8_lossy(n).trim().to_string());
+                    }
+                }
+            }
+            assert!(names.len() > 60_000);
+
+            let mut failures = Vec::new();
+            for raw in &names {
+                let name = match parse_name(raw) {
+                    Ok(n) => n,
+                    Err(_) => continue,
+                };
+                let full = name.render(NameStyle::Full);
+                if parse_name(&full).as_ref() != Ok(&name) {
+                    failures.push((raw.clone(), full));
+                }
+            }
+            assert_eq!(failures, Vec::<(String, String)>::new());
         }
     }
 }
@@ -1,11 +1,27 @@
+use crate::cli::Rollup;
 use crate::plant::PlantCSV;
-use crate::plant::PlantName;
+use crate::plant::{Authorship, NameStyle, PlantName};
+use crate::report::RegionMatch;
+use crate::snapshot::{Snapshot, SnapshotFile, SnapshotRecord};
 use crate::species::SpeciesName;
 use crate::util::Region;
-use nom::lib::std::collections::HashSet;
-use rusqlite::{Connection, Error, Statement};
+use crate::vascan::VascanDistribution;
+use rusqlite::{named_params, params, Connection, Error, Statement};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use strum_macros::Display;
 
+/// the version of plants.sql. it is bumped whenever a change to the plant tables can not be made to an existing DB by
+/// re-running the schema, e.g., a new column, so that the tables are rebuilt.
+pub const SCHEMA_VERSION: i64 = 6;
+
+/// a region in the range of a name, see `select_plant_regions`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlantRegion {
+    pub matched: RegionMatch,
+    /// the USDA records, the symbol and name, of the plants that put the region in the range
+    pub records: Vec<String>,
+}
+
 #[derive(Display, Debug)]
 pub enum AliasType {
     #[strum(serialize = "common")]
@@ -30,6 +46,14 @@ pub struct PlantDB<'a> {
     plant_name_exists_statement: Option<Statement<'a>>,
     select_plant_regions_statement: Option<Statement<'a>>,
     select_all_regions_statement: Option<Statement<'a>>,
+    create_snapshot_record_statement: Option<Statement<'a>>,
+    create_plant_parent_statement: Option<Statement<'a>>,
+    select_accepted_names_statement: Option<Statement<'a>>,
+    select_synonym_names_statement: Option<Statement<'a>>,
+    select_common_names_statement: Option<Statement<'a>>,
+    select_common_names_by_region_statement: Option<Statement<'a>>,
+    select_family_statement: Option<Statement<'a>>,
+    create_vascan_distribution_statement: Option<Statement<'a>>,
 }
 
 impl<'a> PlantDB<'a> {
@@ -47,6 +71,14 @@ impl<'a> PlantDB<'a> {
             plant_name_exists_statement: None,
             select_plant_regions_statement: None,
             select_all_regions_statement: None,
+            create_snapshot_record_statement: None,
+            create_plant_parent_statement: None,
+            select_accepted_names_statement: None,
+            select_synonym_names_statement: None,
+            select_common_names_statement: None,
+            select_common_names_by_region_statement: None,
+            select_family_statement: None,
+            create_vascan_distribution_statement: None,
         }
     }
 
@@ -83,19 +115,19 @@ impl<'a> PlantDB<'a> {
         Ok(row.is_some())
     }
 
-    /// fetches an ID for a Plant by name
-    pub fn select_plantid(&mut self, name: &str) -> Result<i64, Error> {
+    /// fetches an ID for a Plant by its USDA symbol and name
+    pub fn select_plantid(&mut self, symbol: &str, name: &str) -> Result<i64, Error> {
         if self.select_plantid_statement.is_none() {
             let stmt = self
                 .conn
-                .prepare("SELECT id FROM plant WHERE rawname = :rawname;")?;
+                .prepare("SELECT id FROM plant WHERE symbol = :symbol AND rawname = :rawname;")?;
             self.select_plantid_statement = Some(stmt);
         };
         let mut rows = self
             .select_plantid_statement
             .as_mut()
             .unwrap()
-            .query(&[(":rawname", &name)])?;
+            .query(&[(":symbol", &symbol), (":rawname", &name)])?;
         let row = rows.next()?;
         let id = row
             .map(|r| r.get(0).expect("Failed to fetch plant id."))
@@ -123,19 +155,32 @@ impl<'a> PlantDB<'a> {
         Ok(id)
     }
 
-    pub fn select_plant_regions(&mut self, name: SpeciesName) -> Result<HashSet<Region>, Error> {
+    /// fetches the regions of the accepted plants with a name along with how each region matched. an infraspecific name
+    /// only matches plants of the same rank and epithet. a species matches the plants of the species and, if the policy
+    /// is to roll them up, all of its infraspecific taxa. a region is only a rollup if the species itself is not in it.
+    /// synonyms are left out, a name that is only a synonym has to be resolved with `select_accepted_names` first.
+    pub fn select_plant_regions(
+        &mut self,
+        name: SpeciesName,
+        rollup: Rollup,
+    ) -> Result<HashMap<Region, PlantRegion>, Error> {
         if self.select_plant_regions_statement.is_none() {
             let stmt = self.conn.prepare(
                 "SELECT r.id, 
                     r.name,
-                    r.code
+                    r.code,
+                    p.type,
+                    p.sspvar,
+                    p.symbol || ' ' || p.rawname
                 FROM plant AS p
                     INNER JOIN
                     plantregion AS pr ON (pr.plant_id = p.id) 
                     INNER JOIN
                     region AS r ON (r.id = pr.region_id) 
                 WHERE genus = :genus AND 
-                    specific = :specific;",
+                    specific = :specific AND 
+                    accepted_id IS NULL
+                ORDER BY p.rawname;",
             )?;
             self.select_plant_regions_statement = Some(stmt);
         };
@@ -146,53 +191,384 @@ impl<'a> PlantDB<'a> {
             .query_map(
                 &[(":genus", &name.genus), (":specific", &name.specific)],
                 |row| {
-                    Ok(Region {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        code: row.get(2)?,
-                        typ: "state".to_string(),
-                    })
+                    Ok((
+                        Region {
+                            id: row.get(0)?,
+                            name: row.get(1)?,
+                            code: row.get(2)?,
+                            typ: "state".to_string(),
+                        },
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                    ))
                 },
             )?;
 
-        let mut regions = HashSet::new();
-        for region in rows {
-            regions.insert(region?);
+        let mut regions: HashMap<Region, PlantRegion> = HashMap::new();
+        for row in rows {
+            let (region, typ, sspvar, record) = row?;
+            let matched = match &name.ssp {
+                Some(ssp) if *ssp == sspvar && typ == name.rank.to_string() => RegionMatch::Exact,
+                Some(_) => continue,
+                None if sspvar.is_empty() => RegionMatch::Exact,
+                None if rollup == Rollup::Species => RegionMatch::Rollup,
+                None => continue,
+            };
+            let r = regions.entry(region).or_insert(PlantRegion {
+                matched,
+                records: Vec::new(),
+            });
+            if matched == RegionMatch::Exact {
+                r.matched = matched;
+            }
+            r.records.push(record);
         }
         Ok(regions)
     }
 
-    /// inserts a plant, if it does not already exist. uniqueness is determined by name. returns the id of the plant.
+    /// fetches the distinct names of all of the plants, written as gallformers writes them, e.g., `Quercus x leana`
+    pub fn select_plant_names(&mut self) -> Result<Vec<String>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT genus, specific, type, sspvar, nothogenus, nothospecies FROM plant 
+                ORDER BY genus, specific, type, sspvar;",
+        )?;
+        let rows = stmt.query_map([], |r| Ok(name_from_row(r)?.render(NameStyle::Gallformers)))?;
+        let mut names: Vec<String> = rows.collect::<Result<_, _>>()?;
+        names.dedup();
+        Ok(names)
+    }
+
+    /// fetches the accepted taxa that a name is a synonym of. it is empty if the name is an accepted taxon itself, or
+    /// is not in the plants DB at all. there can be more than one if the name is a synonym of parts of several taxa.
+    pub fn select_accepted_names(&mut self, name: &SpeciesName) -> Result<Vec<PlantName>, Error> {
+        if self.select_accepted_names_statement.is_none() {
+            let stmt = self.conn.prepare(
+            "SELECT DISTINCT a.genus, a.specific, a.type, a.sspvar, a.nothogenus, a.nothospecies
+                FROM plant AS s
+                    INNER JOIN
+                    plant AS a ON (a.id = s.accepted_id) 
+                WHERE s.genus = :genus AND 
+                    s.specific = :specific AND 
+                    s.sspvar = :sspvar AND 
+                    (:sspvar = '' OR s.type = :type) AND 
+                    NOT EXISTS (
+                        SELECT 1 FROM plant AS p 
+                        WHERE p.genus = :genus AND 
+                            p.specific = :specific AND 
+                            p.sspvar = :sspvar AND 
+                            (:sspvar = '' OR p.type = :type) AND 
+                            p.accepted_id IS NULL
+                    )
+                ORDER BY a.genus, a.specific, a.type, a.sspvar;",
+            )?;
+            self.select_accepted_names_statement = Some(stmt);
+        };
+        let rows = self
+            .select_accepted_names_statement
+            .as_mut()
+            .unwrap()
+            .query_map(
+                named_params! {
+                    ":genus": name.genus,
+                    ":specific": name.specific,
+                    ":type": name.rank.to_string(),
+                    ":sspvar": name.ssp.as_deref().unwrap_or_default(),
+                },
+                name_from_row,
+            )?;
+        rows.collect()
+    }
+
+    /// fetches the family of the accepted taxon with exactly the name, e.g., `Fagaceae` for `Quercus alba`.
+    pub fn select_family(&mut self, name: &SpeciesName) -> Result<Option<String>, Error> {
+        if self.select_family_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT family FROM plant 
+                    WHERE genus = :genus AND 
+                        specific = :specific AND 
+                        sspvar = :sspvar AND 
+                        (:sspvar = '' OR type = :type) AND 
+                        accepted_id IS NULL AND 
+                        family != ''
+                    LIMIT 1;",
+            )?;
+            self.select_family_statement = Some(stmt);
+        };
+        let mut rows = self.select_family_statement.as_mut().unwrap().query_map(
+            named_params! {
+                ":genus": name.genus,
+                ":specific": name.specific,
+                ":type": name.rank.to_string(),
+                ":sspvar": name.ssp.as_deref().unwrap_or_default(),
+            },
+            |r| r.get(0),
+        )?;
+        rows.next().transpose()
+    }
+
+    /// fetches the common names of the accepted taxon with exactly the name, tidied up with `normalise_common_name`.
+    /// names that only differ in case or punctuation, e.g., `sugar-maple` and `Sugar Maple`, are only returned once.
+    pub fn select_common_names(&mut self, name: &SpeciesName) -> Result<Vec<String>, Error> {
+        if self.select_common_names_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT al.name
+                    FROM plant AS p
+                        INNER JOIN
+                        plantalias AS pa ON (pa.plant_id = p.id) 
+                        INNER JOIN
+                        alias AS al ON (al.id = pa.alias_id) 
+                    WHERE p.genus = :genus AND 
+                        p.specific = :specific AND 
+                        p.sspvar = :sspvar AND 
+                        (:sspvar = '' OR p.type = :type) AND 
+                        p.accepted_id IS NULL AND 
+                        pa.type = :alias_type
+                    ORDER BY al.name COLLATE NOCASE, al.name DESC;",
+            )?;
+            self.select_common_names_statement = Some(stmt);
+        };
+        let rows = self
+            .select_common_names_statement
+            .as_mut()
+            .unwrap()
+            .query_map(
+                named_params! {
+                    ":genus": name.genus,
+                    ":specific": name.specific,
+                    ":type": name.rank.to_string(),
+                    ":sspvar": name.ssp.as_deref().unwrap_or_default(),
+                    ":alias_type": AliasType::Common.to_string(),
+                },
+                |r| r.get::<_, String>(0),
+            )?;
+        // sorted so that of the names that are the same, the lower case one with spaces is kept
+        let mut names: Vec<String> = Vec::new();
+        for row in rows {
+            let n = normalise_common_name(&row?);
+            if !n.is_empty()
+                && !names
+                    .iter()
+                    .any(|o| common_name_key(o) == common_name_key(&n))
+            {
+                names.push(n);
+            }
+        }
+        Ok(names)
+    }
+
+    /// fetches the common names of the accepted taxon with exactly the name, keyed by the code of each region that uses
+    /// them, e.g., `MN`. the names are tidied up with `normalise_common_name` but, unlike `select_common_names`, a name
+    /// is kept in every region that uses it.
+    pub fn select_common_names_by_region(
+        &mut self,
+        name: &SpeciesName,
+    ) -> Result<BTreeMap<String, Vec<String>>, Error> {
+        if self.select_common_names_by_region_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT r.code, al.name
+                    FROM plant AS p
+                        INNER JOIN
+                        plantalias AS pa ON (pa.plant_id = p.id) 
+                        INNER JOIN
+                        alias AS al ON (al.id = pa.alias_id) 
+                        INNER JOIN
+                        region AS r ON (r.id = pa.region_id) 
+                    WHERE p.genus = :genus AND 
+                        p.specific = :specific AND 
+                        p.sspvar = :sspvar AND 
+                        (:sspvar = '' OR p.type = :type) AND 
+                        p.accepted_id IS NULL AND 
+                        pa.type = :alias_type
+                    ORDER BY r.code, al.name;",
+            )?;
+            self.select_common_names_by_region_statement = Some(stmt);
+        };
+        let rows = self
+            .select_common_names_by_region_statement
+            .as_mut()
+            .unwrap()
+            .query_map(
+                named_params! {
+                    ":genus": name.genus,
+                    ":specific": name.specific,
+                    ":type": name.rank.to_string(),
+                    ":sspvar": name.ssp.as_deref().unwrap_or_default(),
+                    ":alias_type": AliasType::Common.to_string(),
+                },
+                |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)),
+            )?;
+        let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for row in rows {
+            let (code, n) = row?;
+            let n = normalise_common_name(&n);
+            let region = names.entry(code).or_default();
+            if !n.is_empty() && !region.contains(&n) {
+                region.push(n);
+            }
+        }
+        Ok(names)
+    }
+
+    /// fetches the distinct synonyms of the accepted taxon with exactly the name, written as gallformers writes them.
+    /// misapplied names, e.g., `Acer saccharum auct. non Marshall`, are not synonyms so they are left out.
+    pub fn select_synonym_names(&mut self, name: &SpeciesName) -> Result<Vec<String>, Error> {
+        if self.select_synonym_names_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "SELECT s.genus, s.specific, s.type, s.sspvar, s.nothogenus, s.nothospecies, s.author
+                    FROM plant AS a
+                        INNER JOIN
+                        plant AS s ON (s.accepted_id = a.id) 
+                    WHERE a.genus = :genus AND 
+                        a.specific = :specific AND 
+                        a.sspvar = :sspvar AND 
+                        (:sspvar = '' OR a.type = :type)
+                    ORDER BY s.genus, s.specific, s.type, s.sspvar;",
+            )?;
+            self.select_synonym_names_statement = Some(stmt);
+        };
+        let rows = self
+            .select_synonym_names_statement
+            .as_mut()
+            .unwrap()
+            .query_map(
+                named_params! {
+                    ":genus": name.genus,
+                    ":specific": name.specific,
+                    ":type": name.rank.to_string(),
+                    ":sspvar": name.ssp.as_deref().unwrap_or_default(),
+                },
+                |r| Ok((name_from_row(r)?, r.get::<_, String>(6)?)),
+            )?;
+        let mut names = Vec::new();
+        for row in rows {
+            let (synonym, author) = row?;
+            let n = synonym.render(NameStyle::Gallformers);
+            if !Authorship::parse(&author).is_misapplied() && !names.contains(&n) {
+                names.push(n);
+            }
+        }
+        Ok(names)
+    }
+
+    /// points each synonym at the accepted taxon with the same USDA symbol. returns the number of synonyms that have an
+    /// accepted taxon, the others are treated as accepted since their accepted taxon could not be imported.
+    pub fn link_synonyms(&mut self) -> Result<i64, Error> {
+        self.conn.execute(
+            "UPDATE plant SET accepted_id = CASE WHEN symbolsynonym = '' THEN NULL ELSE (
+                SELECT a.id FROM plant AS a
+                 WHERE a.symbol = plant.symbol
+                   AND a.symbolsynonym = ''
+                 ORDER BY a.id LIMIT 1) END;",
+            [],
+        )?;
+        self.conn.query_row(
+            "SELECT count(*) FROM plant WHERE accepted_id IS NOT NULL;",
+            [],
+            |r| r.get(0),
+        )
+    }
+
+    /// inserts a plant, or updates it if it already exists. uniqueness is determined by the USDA symbol and the name.
+    /// returns the id of the plant.
     pub fn create_plant(&mut self, plant: &PlantCSV, name: PlantName) -> Result<i64, Error> {
         if self.create_plant_statement.is_none() {
-            let stmt = self.conn.prepare("INSERT OR IGNORE INTO plant (rawname, symbol, symbolsynonym, family, genus, specific, type, sspvar, hybridpair, author, secondauthor) VALUES (:rawname, :symbol, :symbolsynonym, :family, :genus, :specific, :type, :sspvar, :hybridpair, :author, :secondauthor)")?;
+            let stmt = self.conn.prepare(
+                "INSERT INTO plant (rawname, symbol, symbolsynonym, family, genus, specific, type, sspvar, parentranks, nothogenus, nothospecies, formula, author, secondauthor, status) 
+                    VALUES (:rawname, :symbol, :symbolsynonym, :family, :genus, :specific, :type, :sspvar, :parentranks, :nothogenus, :nothospecies, :formula, :author, :secondauthor, :status)
+                ON CONFLICT (symbol, rawname) DO UPDATE SET
+                    symbolsynonym = excluded.symbolsynonym,
+                    family = excluded.family,
+                    genus = excluded.genus,
+                    specific = excluded.specific,
+                    type = excluded.type,
+                    sspvar = excluded.sspvar,
+                    parentranks = excluded.parentranks,
+                    nothogenus = excluded.nothogenus,
+                    nothospecies = excluded.nothospecies,
+                    formula = excluded.formula,
+                    author = excluded.author,
+                    secondauthor = excluded.secondauthor,
+                    status = excluded.status;",
+            )?;
             self.create_plant_statement = Some(stmt);
         };
         // println!("Creating plant {:?} -- {:?}", plant, name);
-        self.create_plant_statement.as_mut().unwrap().execute(&[
-            (":rawname", &plant.name),
-            (":symbol", &plant.symbol),
-            (":symbolsynonym", &plant.syn_symbol),
-            (":family", &plant.family),
-            (":genus", &name.genus),
-            (":specific", &name.specific),
-            (":type", &name.species_type.to_string()),
-            (":sspvar", &name.sspvar.unwrap_or_else(|| "".to_string())),
-            (
-                ":hybridpair",
-                &name
-                    .hybrid
-                    .map(|(a, b)| format!("{},{}", a, b))
-                    .unwrap_or_else(|| "".to_string()),
-            ),
-            (":author", &name.author.unwrap_or_else(|| "".to_string())),
-            (
-                ":secondauthor",
-                &name.second_author.unwrap_or_else(|| "".to_string()),
-            ),
-        ])?;
+        self.create_plant_statement
+            .as_mut()
+            .unwrap()
+            .execute(named_params! {
+                ":rawname": plant.name,
+                ":symbol": plant.symbol,
+                ":symbolsynonym": plant.syn_symbol,
+                ":family": plant.family,
+                ":genus": name.genus,
+                ":specific": name.specific,
+                ":type": name.species_type.to_string(),
+                ":sspvar": name.sspvar.as_deref().unwrap_or_default(),
+                ":parentranks": parent_ranks(&name),
+                ":nothogenus": name.nothogenus,
+                ":nothospecies": name.nothospecies,
+                ":formula": name.formula.as_deref().unwrap_or_default(),
+                ":author": name.author.as_deref().unwrap_or_default(),
+                ":secondauthor": name.second_author.as_deref().unwrap_or_default(),
+                ":status": name.status.map(|s| s.to_string()).unwrap_or_default(),
+            })?;
 
-        self.select_plantid(&plant.name)
+        let plant_id = self.select_plantid(&plant.symbol, &plant.name)?;
+        self.create_plant_parents(plant_id, &name.parents)?;
+        Ok(plant_id)
+    }
+
+    /// replaces the parents of a hybrid. they are linked to their plants by `link_plant_parents` once all of the
+    /// plants have been imported.
+    fn create_plant_parents(&mut self, plant_id: i64, parents: &[PlantName]) -> Result<(), Error> {
+        self.conn
+            .execute("DELETE FROM plantparent WHERE plant_id = ?1;", [plant_id])?;
+        if self.create_plant_parent_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "INSERT INTO plantparent (plant_id, position, genus, specific, type, sspvar, parentranks) 
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+            )?;
+            self.create_plant_parent_statement = Some(stmt);
+        };
+        for (position, parent) in parents.iter().enumerate() {
+            self.create_plant_parent_statement
+                .as_mut()
+                .unwrap()
+                .execute(params![
+                    plant_id,
+                    position as i64,
+                    parent.genus,
+                    parent.specific,
+                    parent.species_type.to_string(),
+                    parent.sspvar.as_deref().unwrap_or_default(),
+                    parent_ranks(parent),
+                ])?;
+        }
+        Ok(())
+    }
+
+    /// points each hybrid parent at the accepted plant with the same name. returns the number of parents that have a
+    /// plant.
+    pub fn link_plant_parents(&mut self) -> Result<i64, Error> {
+        self.conn.execute(
+            "UPDATE plantparent SET parent_id = (
+                SELECT p.id FROM plant AS p
+                 WHERE p.genus = plantparent.genus
+                   AND p.specific = plantparent.specific
+                   AND p.type = plantparent.type
+                   AND p.sspvar = plantparent.sspvar
+                   AND p.parentranks = plantparent.parentranks
+                   AND p.symbolsynonym = ''
+                 ORDER BY p.id LIMIT 1);",
+            [],
+        )?;
+        self.conn.query_row(
+            "SELECT count(*) FROM plantparent WHERE parent_id IS NOT NULL;",
+            [],
+            |r| r.get(0),
+        )
     }
 
     /// inserts a alias name, if it does not already exist. returns the id of the alias.
@@ -209,18 +585,20 @@ impl<'a> PlantDB<'a> {
             .unwrap()
             .execute(&[(":name", &name)])?;
 
-        self.select_aliasid(&name)
+        self.select_aliasid(name)
     }
 
-    /// creates a relationship between a alias name and a plant.
+    /// creates a relationship between a alias name and a plant in the region whose checklist uses the name, if it does
+    /// not already exist.
     pub fn relate_alias_to_plant(
         &mut self,
         alias_id: &str,
         alias_type: AliasType,
         plant_id: &str,
+        region_id: &str,
     ) -> Result<(), Error> {
         if self.relate_alias_to_plant_statement.is_none() {
-            let stmt = self.conn.prepare("INSERT INTO plantalias (plant_id, alias_id, type) VALUES (:plant_id, :alias_id, :alias_type)")?;
+            let stmt = self.conn.prepare("INSERT OR IGNORE INTO plantalias (plant_id, alias_id, type, region_id) VALUES (:plant_id, :alias_id, :alias_type, :region_id)")?;
             self.relate_alias_to_plant_statement = Some(stmt);
         };
         // println!("Relating plant {} to aliasname {}", plant_id, alias_id);
@@ -231,16 +609,17 @@ impl<'a> PlantDB<'a> {
                 (":plant_id", &plant_id),
                 (":alias_type", &alias_type.to_string().as_ref()),
                 (":alias_id", &alias_id),
+                (":region_id", &region_id),
             ])?;
 
         Ok(())
     }
 
-    /// creates a relationship between a plant and a region.
+    /// creates a relationship between a plant and a region, if it does not already exist.
     pub fn create_plant_region(&mut self, plant_id: &str, region_id: &str) -> Result<i64, Error> {
         if self.create_plant_region_statement.is_none() {
             let stmt = self.conn.prepare(
-                "INSERT INTO plantregion (plant_id, region_id) VALUES (:plant_id, :region_id)",
+                "INSERT OR IGNORE INTO plantregion (plant_id, region_id) VALUES (:plant_id, :region_id)",
             )?;
             self.create_plant_region_statement = Some(stmt);
         };
@@ -253,12 +632,13 @@ impl<'a> PlantDB<'a> {
         Ok(self.conn.last_insert_rowid())
     }
 
-    /// inserts a new region. If the region already exists this will fail.
-    pub fn create_region(&mut self, name: &str, code: &str) -> Result<i64, Error> {
+    /// inserts a region, or updates its name if a region with the code already exists. returns the id of the region.
+    pub fn create_or_fetch_region(&mut self, name: &str, code: &str) -> Result<i64, Error> {
         if self.create_region_statement.is_none() {
-            let stmt = self
-                .conn
-                .prepare("INSERT INTO region (name, code) VALUES (:name, :code)")?;
+            let stmt = self.conn.prepare(
+                "INSERT INTO region (name, code) VALUES (:name, :code)
+                    ON CONFLICT (code) DO UPDATE SET name = excluded.name;",
+            )?;
             self.create_region_statement = Some(stmt);
         };
         // println!("Creating region {} - {}", name, code);
@@ -267,7 +647,23 @@ impl<'a> PlantDB<'a> {
             .unwrap()
             .execute(&[(":name", &name), (":code", &code)])?;
 
-        Ok(self.conn.last_insert_rowid())
+        self.conn
+            .query_row("SELECT id FROM region WHERE code = ?1;", [code], |r| {
+                r.get(0)
+            })
+    }
+
+    /// removes all of the plants, and the common names they have there, from a region. returns the number of plants that
+    /// were in the region.
+    pub fn delete_plant_regions(&mut self, region_id: i64) -> Result<usize, Error> {
+        self.conn.execute(
+            "DELETE FROM plantalias WHERE region_id = :region_id;",
+            &[(":region_id", &region_id)],
+        )?;
+        self.conn.execute(
+            "DELETE FROM plantregion WHERE region_id = :region_id;",
+            &[(":region_id", &region_id)],
+        )
     }
 
     pub fn select_all_regions(&mut self) -> Result<Vec<Region>, Error> {
@@ -294,4 +690,729 @@ impl<'a> PlantDB<'a> {
         }
         Ok(regions)
     }
+
+    /// records a new snapshot of the USDA checklist. returns the id of the snapshot.
+    pub fn create_snapshot(&mut self, imported: &str, downloaded: &str) -> Result<i64, Error> {
+        self.conn.execute(
+            "INSERT INTO snapshot (imported, downloaded) VALUES (:imported, :downloaded);",
+            &[(":imported", &imported), (":downloaded", &downloaded)],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn create_snapshot_file(
+        &mut self,
+        snapshot_id: i64,
+        file: &SnapshotFile,
+    ) -> Result<(), Error> {
+        self.conn.execute(
+            "INSERT INTO snapshotfile (snapshot_id, region_code, filename, sha256, rows) 
+                VALUES (?1, ?2, ?3, ?4, ?5);",
+            params![
+                snapshot_id,
+                file.region_code,
+                file.filename,
+                file.sha256,
+                file.rows
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// records a row from one of the checklist CSVs as part of a snapshot.
+    pub fn create_snapshot_record(
+        &mut self,
+        snapshot_id: i64,
+        region_code: &str,
+        plant: &PlantCSV,
+    ) -> Result<(), Error> {
+        if self.create_snapshot_record_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "INSERT INTO snapshotrecord (snapshot_id, region_code, symbol, symbolsynonym, rawname, family) 
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+            )?;
+            self.create_snapshot_record_statement = Some(stmt);
+        };
+        self.create_snapshot_record_statement
+            .as_mut()
+            .unwrap()
+            .execute(params![
+                snapshot_id,
+                region_code,
+                plant.symbol,
+                plant.syn_symbol,
+                plant.name,
+                plant.family
+            ])?;
+        Ok(())
+    }
+
+    /// fetches all of the snapshots, oldest first
+    pub fn select_snapshots(&mut self) -> Result<Vec<Snapshot>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, imported, downloaded FROM snapshot ORDER BY id;")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(Snapshot {
+                id: r.get(0)?,
+                imported: r.get(1)?,
+                downloaded: r.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn select_snapshot_files(&mut self, snapshot_id: i64) -> Result<Vec<SnapshotFile>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT region_code, filename, sha256, rows FROM snapshotfile 
+                WHERE snapshot_id = ?1 ORDER BY region_code;",
+        )?;
+        let rows = stmt.query_map([snapshot_id], |r| {
+            Ok(SnapshotFile {
+                region_code: r.get(0)?,
+                filename: r.get(1)?,
+                sha256: r.get(2)?,
+                rows: r.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// fetches the number of rows in the most recent import of each region. an older DB without any snapshot tables
+    /// has no imports to report.
+    pub fn select_latest_snapshot_rows(&mut self) -> Result<BTreeMap<String, i64>, Error> {
+        let has_snapshots: bool = self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'snapshotfile');",
+            [],
+            |r| r.get(0),
+        )?;
+        if !has_snapshots {
+            return Ok(BTreeMap::new());
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT region_code, rows FROM snapshotfile sf
+                WHERE snapshot_id = (SELECT MAX(snapshot_id) FROM snapshotfile WHERE region_code = sf.region_code);",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// the regions that a snapshot still has the records of, see `prune_snapshot_records`
+    pub fn select_snapshot_record_regions(
+        &mut self,
+        snapshot_id: i64,
+    ) -> Result<BTreeSet<String>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT region_code FROM snapshotrecord WHERE snapshot_id = ?1;")?;
+        let rows = stmt.query_map([snapshot_id], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    /// deletes the raw records of all but the `keep` most recent snapshots of each region. a region's most recent
+    /// snapshot is not necessarily the latest one as a snapshot only has the regions that were imported. the snapshots
+    /// and their files are kept. returns the number of records that were deleted.
+    pub fn prune_snapshot_records(&mut self, keep: usize) -> Result<usize, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT snapshot_id, region_code FROM snapshotfile ORDER BY region_code, snapshot_id DESC;",
+        )?;
+        let files = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut deleted = 0;
+        let mut kept = 0;
+        let mut region = "";
+        for (snapshot_id, region_code) in &files {
+            if region != region_code {
+                region = region_code;
+                kept = 0;
+            }
+            if kept < keep {
+                kept += 1;
+                continue;
+            }
+            deleted += self.conn.execute(
+                "DELETE FROM snapshotrecord WHERE snapshot_id = ?1 AND region_code = ?2;",
+                params![snapshot_id, region_code],
+            )?;
+        }
+        Ok(deleted)
+    }
+
+    pub fn select_snapshot_records(
+        &mut self,
+        snapshot_id: i64,
+    ) -> Result<Vec<SnapshotRecord>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT region_code, symbol, symbolsynonym, rawname, family FROM snapshotrecord 
+                WHERE snapshot_id = ?1;",
+        )?;
+        let rows = stmt.query_map([snapshot_id], |r| {
+            Ok(SnapshotRecord {
+                region_code: r.get(0)?,
+                symbol: r.get(1)?,
+                symbol_syn: r.get(2)?,
+                name: r.get(3)?,
+                family: r.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// deletes the distributions of an earlier VASCAN import so that only the latest one is kept. returns the number of
+    /// places that were deleted.
+    pub fn delete_vascan_distribution(&mut self) -> Result<usize, Error> {
+        self.conn.execute("DELETE FROM vascandistribution;", [])
+    }
+
+    pub fn create_vascan_distribution(&mut self, d: &VascanDistribution) -> Result<(), Error> {
+        if self.create_vascan_distribution_statement.is_none() {
+            let stmt = self.conn.prepare(
+                "INSERT OR REPLACE INTO vascandistribution (host, taxon_id, scientificname, location_id, locality, establishment, occurrence, apiversion, lastupdated) 
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+            )?;
+            self.create_vascan_distribution_statement = Some(stmt);
+        };
+        self.create_vascan_distribution_statement
+            .as_mut()
+            .unwrap()
+            .execute(params![
+                d.host,
+                d.taxon_id,
+                d.scientific_name,
+                d.location_id,
+                d.locality,
+                d.establishment_means,
+                d.occurrence_status,
+                d.api_version,
+                d.last_updated
+            ])?;
+        Ok(())
+    }
+
+    /// fetches the distributions from the latest VASCAN import, ordered by host and locality
+    pub fn select_vascan_distribution(&mut self) -> Result<Vec<VascanDistribution>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT host, taxon_id, scientificname, location_id, locality, establishment, occurrence, apiversion, lastupdated 
+                FROM vascandistribution ORDER BY host, locality;",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok(VascanDistribution {
+                host: r.get(0)?,
+                taxon_id: r.get(1)?,
+                scientific_name: r.get(2)?,
+                location_id: r.get(3)?,
+                locality: r.get(4)?,
+                establishment_means: r.get(5)?,
+                occurrence_status: r.get(6)?,
+                api_version: r.get(7)?,
+                last_updated: r.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// reads a name from the genus, specific, type, sspvar, nothogenus and nothospecies columns of a row
+fn name_from_row(r: &rusqlite::Row) -> Result<PlantName, Error> {
+    let typ: String = r.get(2)?;
+    let sspvar: String = r.get(3)?;
+    Ok(PlantName {
+        genus: r.get(0)?,
+        specific: r.get(1)?,
+        species_type: typ.parse().unwrap_or_default(),
+        sspvar: Some(sspvar).filter(|s| !s.is_empty()),
+        nothogenus: r.get(4)?,
+        nothospecies: r.get(5)?,
+        ..Default::default()
+    })
+}
+
+/// tidies up a common name as USDA gives it: runs of whitespace become a single space, curly quotes become straight
+/// ones, trailing punctuation is dropped and a name in all capitals is lower cased. USDA capitalises proper nouns, e.g.,
+/// `Drummond's rockcress`, so otherwise the case is kept.
+pub fn normalise_common_name(name: &str) -> String {
+    let name = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(['\u{2018}', '\u{2019}'], "'")
+        .replace(['\u{201c}', '\u{201d}'], "\"");
+    let name = name
+        .trim_end_matches(|c: char| c.is_ascii_punctuation() && c != '\'' && c != ')')
+        .to_string();
+    if name.chars().any(char::is_lowercase) {
+        name
+    } else {
+        name.to_lowercase()
+    }
+}
+
+/// what two common names have to share to be the same name: the letters, ignoring case, and the breaks between words,
+/// ignoring whether they are spaces or hyphens
+fn common_name_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the ranks above the plant's own for a quadrinomial, e.g., "ssp. americana"
+fn parent_ranks(name: &PlantName) -> String {
+    name.ranks
+        .iter()
+        .map(|r| format!("{} {}", r.rank, r.epithet))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plant_csv(name: &str) -> PlantCSV {
+        PlantCSV {
+            symbol: "QUAL".to_string(),
+            syn_symbol: "".to_string(),
+            name: name.to_string(),
+            common_name: "white oak".to_string(),
+            family: "Fagaceae".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reimport_is_idempotent() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        for _ in 0..2 {
+            // the schema is re-applied on every import
+            db.conn
+                .execute_batch(include_str!("../plants.sql"))
+                .unwrap();
+            let region = db.create_or_fetch_region("Minnesota", "MN").unwrap();
+            let p = plant_csv("Quercus alba L.");
+            let id = db
+                .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+                .unwrap();
+            let alias = db.create_alias(&p.common_name).unwrap();
+            db.relate_alias_to_plant(
+                &alias.to_string(),
+                AliasType::Common,
+                &id.to_string(),
+                &region.to_string(),
+            )
+            .unwrap();
+            db.create_plant_region(&id.to_string(), &region.to_string())
+                .unwrap();
+        }
+
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT count(*) FROM {};", table), [], |r| {
+                r.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(count("plant"), 1);
+        assert_eq!(count("region"), 1);
+        assert_eq!(count("alias"), 1);
+        assert_eq!(count("plantalias"), 1);
+        assert_eq!(count("plantregion"), 1);
+
+        let region = db.create_or_fetch_region("Minnesota", "MN").unwrap();
+        assert_eq!(db.delete_plant_regions(region).unwrap(), 1);
+        assert_eq!(count("plantregion"), 0);
+    }
+
+    #[test]
+    fn test_status_names_link_to_accepted() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        let accepted = PlantCSV {
+            symbol: "VAVE".to_string(),
+            name: "Vachellia vernicosa (Britton & Rose) Seigler & Ebinger".to_string(),
+            ..plant_csv("")
+        };
+        let illeg = PlantCSV {
+            symbol: "VAVE".to_string(),
+            syn_symbol: "ACVE".to_string(),
+            name: "Acacia vernicosa Standl., nom. illeg.".to_string(),
+            ..plant_csv("")
+        };
+        let accepted_id = db
+            .create_plant(&accepted, PlantName::new(accepted.name.clone()).unwrap())
+            .unwrap();
+        let illeg_id = db
+            .create_plant(&illeg, PlantName::new(illeg.name.clone()).unwrap())
+            .unwrap();
+
+        let (status, author, linked): (String, String, i64) = conn
+            .query_row(
+                "SELECT s.status, p.author, s.accepted_id FROM plantstatus s JOIN plant p ON p.id = s.plant_id 
+                    WHERE s.plant_id = ?1;",
+                [illeg_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(status, "nom. illeg.");
+        assert_eq!(author, "Standl.");
+        assert_eq!(linked, accepted_id);
+    }
+
+    #[test]
+    fn test_hybrid_parents_link_to_plants() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        let mut create = |symbol: &str, name: &str| {
+            let p = PlantCSV {
+                symbol: symbol.to_string(),
+                ..plant_csv(name)
+            };
+            db.create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+                .unwrap()
+        };
+        let alba = create("QUAL", "Quercus alba L.");
+        let name = "Quercus ×beadlei Trel. ex Palmer (pro sp.) [alba × michauxii]";
+        let hybrid = create("QUBE", name);
+        // re-importing the hybrid replaces its parents rather than adding to them
+        assert_eq!(create("QUBE", name), hybrid);
+        assert_eq!(db.link_plant_parents().unwrap(), 1);
+
+        let (nothospecies, formula): (bool, String) = conn
+            .query_row(
+                "SELECT nothospecies, formula FROM plant WHERE id = ?1;",
+                [hybrid],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert!(nothospecies);
+        assert_eq!(formula, "alba × michauxii");
+
+        let mut stmt = conn
+            .prepare("SELECT specific, parent_id FROM plantparent WHERE plant_id = ?1 ORDER BY position;")
+            .unwrap();
+        let parents: Vec<(String, Option<i64>)> = stmt
+            .query_map([hybrid], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            parents,
+            vec![
+                ("alba".to_string(), Some(alba)),
+                ("michauxii".to_string(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_plant_regions_is_rank_aware() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        let mn = db.create_or_fetch_region("Minnesota", "MN").unwrap();
+        let wi = db.create_or_fetch_region("Wisconsin", "WI").unwrap();
+        for (name, region) in [
+            ("Acer saccharum Marshall", mn),
+            (
+                "Acer saccharum Marshall var. nigrum (F. Michx.) Britton",
+                wi,
+            ),
+        ] {
+            let p = plant_csv(name);
+            let id = db
+                .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+                .unwrap();
+            db.create_plant_region(&id.to_string(), &region.to_string())
+                .unwrap();
+        }
+
+        let codes = |db: &mut PlantDB, name: &str, rollup: Rollup| -> Vec<(String, RegionMatch)> {
+            let mut codes: Vec<(String, RegionMatch)> = db
+                .select_plant_regions(SpeciesName::new(name).unwrap(), rollup)
+                .unwrap()
+                .into_iter()
+                .map(|(r, p)| (r.code, p.matched))
+                .collect();
+            codes.sort_by(|a, b| a.0.cmp(&b.0));
+            codes
+        };
+        assert_eq!(
+            codes(&mut db, "Acer saccharum", Rollup::Species),
+            vec![
+                ("MN".to_string(), RegionMatch::Exact),
+                ("WI".to_string(), RegionMatch::Rollup)
+            ]
+        );
+        assert_eq!(
+            codes(&mut db, "Acer saccharum", Rollup::Separate),
+            vec![("MN".to_string(), RegionMatch::Exact)]
+        );
+        for rollup in [Rollup::Species, Rollup::Separate] {
+            assert_eq!(
+                codes(&mut db, "Acer saccharum var. nigrum", rollup),
+                vec![("WI".to_string(), RegionMatch::Exact)]
+            );
+            assert!(codes(&mut db, "Acer saccharum ssp. nigrum", rollup).is_empty());
+        }
+
+        // a region that the species is in as well as a variety is an exact match
+        let p = plant_csv("Acer saccharum Marshall");
+        let id = db
+            .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+            .unwrap();
+        db.create_plant_region(&id.to_string(), &wi.to_string())
+            .unwrap();
+        assert_eq!(
+            codes(&mut db, "Acer saccharum", Rollup::Species),
+            vec![
+                ("MN".to_string(), RegionMatch::Exact),
+                ("WI".to_string(), RegionMatch::Exact)
+            ]
+        );
+
+        // the records of every plant that put the species in the region are kept
+        let regions = db
+            .select_plant_regions(SpeciesName::new("Acer saccharum").unwrap(), Rollup::Species)
+            .unwrap();
+        let records = &regions
+            .iter()
+            .find(|(r, _)| r.code == "WI")
+            .unwrap()
+            .1
+            .records;
+        assert_eq!(
+            records,
+            &vec![
+                "QUAL Acer saccharum Marshall",
+                "QUAL Acer saccharum Marshall var. nigrum (F. Michx.) Britton"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune_snapshot_records() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        // MN is in every snapshot, WI only in the first
+        let p = plant_csv("Quercus alba L.");
+        let mut ids = Vec::new();
+        for n in 0..3 {
+            let id = db.create_snapshot("now", "today").unwrap();
+            let regions: &[&str] = if n == 0 { &["MN", "WI"] } else { &["MN"] };
+            for region in regions {
+                db.create_snapshot_file(
+                    id,
+                    &SnapshotFile {
+                        region_code: region.to_string(),
+                        filename: format!("{}.csv", region),
+                        sha256: String::new(),
+                        rows: 1,
+                    },
+                )
+                .unwrap();
+                db.create_snapshot_record(id, region, &p).unwrap();
+            }
+            ids.push(id);
+        }
+
+        assert_eq!(db.prune_snapshot_records(2).unwrap(), 1);
+        assert_eq!(
+            db.select_snapshot_record_regions(ids[0]).unwrap(),
+            BTreeSet::from(["WI".to_string()])
+        );
+        assert_eq!(db.select_snapshot_records(ids[1]).unwrap().len(), 1);
+        assert_eq!(db.select_snapshot_records(ids[2]).unwrap().len(), 1);
+        assert_eq!(db.select_snapshots().unwrap().len(), 3);
+        assert_eq!(db.prune_snapshot_records(2).unwrap(), 0);
+
+        assert_eq!(db.prune_snapshot_records(1).unwrap(), 1);
+        assert!(db.select_snapshot_records(ids[1]).unwrap().is_empty());
+        assert_eq!(db.select_latest_snapshot_rows().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_synonyms_resolve_to_accepted() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+
+        let mn = db.create_or_fetch_region("Minnesota", "MN").unwrap();
+        let wi = db.create_or_fetch_region("Wisconsin", "WI").unwrap();
+        for (symbol, syn_symbol, name, region) in [
+            ("ACNI5", "", "Acer nigrum Michx. f.", mn),
+            (
+                "ACNI5",
+                "ACSAN",
+                "Acer saccharum Marshall var. nigrum (Michx. f.) Britton",
+                mn,
+            ),
+            ("ACSA3", "", "Acer saccharum Marshall", wi),
+            // a synonym of parts of two taxa
+            ("ACNI5", "ACBA", "Acer barbatum Michx.", mn),
+            ("ACSA3", "ACBA2", "Acer barbatum Michx.", wi),
+            // a misapplied name is not a synonym
+            (
+                "ACSA3",
+                "ACFL",
+                "Acer floridanum auct. non (Chapm.) Pax",
+                wi,
+            ),
+        ] {
+            let p = PlantCSV {
+                symbol: symbol.to_string(),
+                syn_symbol: syn_symbol.to_string(),
+                ..plant_csv(name)
+            };
+            let id = db
+                .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+                .unwrap();
+            db.create_plant_region(&id.to_string(), &region.to_string())
+                .unwrap();
+        }
+        assert_eq!(db.link_synonyms().unwrap(), 4);
+
+        let accepted = |db: &mut PlantDB, name: &str| -> Vec<String> {
+            db.select_accepted_names(&SpeciesName::new(name).unwrap())
+                .unwrap()
+                .iter()
+                .map(|a| a.render(NameStyle::Gallformers))
+                .collect()
+        };
+        assert_eq!(
+            accepted(&mut db, "Acer saccharum var. nigrum"),
+            vec!["Acer nigrum"]
+        );
+        assert_eq!(
+            accepted(&mut db, "Acer barbatum"),
+            vec!["Acer nigrum", "Acer saccharum"]
+        );
+        assert!(accepted(&mut db, "Acer saccharum").is_empty());
+        assert!(accepted(&mut db, "Acer rubrum").is_empty());
+
+        // the synonym's range is not rolled up into the species it was named under
+        let regions = db
+            .select_plant_regions(SpeciesName::new("Acer saccharum").unwrap(), Rollup::Species)
+            .unwrap();
+        assert_eq!(
+            regions.keys().map(|r| r.code.as_str()).collect::<Vec<_>>(),
+            vec!["WI"]
+        );
+
+        let synonyms = |db: &mut PlantDB, name: &str| -> Vec<String> {
+            db.select_synonym_names(&SpeciesName::new(name).unwrap())
+                .unwrap()
+        };
+        assert_eq!(
+            synonyms(&mut db, "Acer nigrum"),
+            vec!["Acer barbatum", "Acer saccharum var. nigrum"]
+        );
+        assert_eq!(synonyms(&mut db, "Acer saccharum"), vec!["Acer barbatum"]);
+        assert!(synonyms(&mut db, "Acer saccharum var. nigrum").is_empty());
+    }
+
+    #[test]
+    fn test_common_names() {
+        assert_eq!(normalise_common_name("  sugar   maple "), "sugar maple");
+        assert_eq!(normalise_common_name("SUGAR MAPLE"), "sugar maple");
+        assert_eq!(
+            normalise_common_name("Drummond\u{2019}s rockcress,"),
+            "Drummond's rockcress"
+        );
+        assert_eq!(
+            common_name_key("Sugar-Maple"),
+            common_name_key("sugar maple")
+        );
+        assert_ne!(
+            common_name_key("sugarmaple"),
+            common_name_key("sugar maple")
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../plants.sql")).unwrap();
+        let mut db = PlantDB::new(&conn);
+        let mn = db.create_or_fetch_region("Minnesota", "MN").unwrap();
+        let wi = db.create_or_fetch_region("Wisconsin", "WI").unwrap();
+        let ia = db.create_or_fetch_region("Iowa", "IA").unwrap();
+        for (name, common, region) in [
+            ("Acer saccharum Marshall", "sugar maple", mn),
+            ("Acer saccharum Marshall", "Sugar-Maple", wi),
+            ("Acer saccharum Marshall", "rock maple.", ia),
+            ("Acer saccharum Marshall", "sugar maple", ia),
+            (
+                "Acer saccharum Marshall var. schneckii Rehder",
+                "Schneck's maple",
+                mn,
+            ),
+        ] {
+            let p = PlantCSV {
+                common_name: common.to_string(),
+                ..plant_csv(name)
+            };
+            let id = db
+                .create_plant(&p, PlantName::new(p.name.clone()).unwrap())
+                .unwrap();
+            let alias = db.create_alias(&p.common_name).unwrap();
+            db.relate_alias_to_plant(
+                &alias.to_string(),
+                AliasType::Common,
+                &id.to_string(),
+                &region.to_string(),
+            )
+            .unwrap();
+        }
+
+        // the variety's common name is not one of the species'
+        assert_eq!(
+            db.select_common_names(&SpeciesName::new("Acer saccharum").unwrap())
+                .unwrap(),
+            vec!["rock maple", "sugar maple"]
+        );
+
+        let by_region = |db: &mut PlantDB| -> Vec<(String, Vec<String>)> {
+            db.select_common_names_by_region(&SpeciesName::new("Acer saccharum").unwrap())
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        let region = |code: &str, names: &[&str]| -> (String, Vec<String>) {
+            (
+                code.to_string(),
+                names.iter().map(|n| n.to_string()).collect(),
+            )
+        };
+        assert_eq!(
+            by_region(&mut db),
+            vec![
+                region("IA", &["rock maple", "sugar maple"]),
+                region("MN", &["sugar maple"]),
+                region("WI", &["Sugar-Maple"]),
+            ]
+        );
+
+        // re-importing a state replaces its names
+        db.delete_plant_regions(wi).unwrap();
+        assert_eq!(
+            by_region(&mut db),
+            vec![
+                region("IA", &["rock maple", "sugar maple"]),
+                region("MN", &["sugar maple"]),
+            ]
+        );
+    }
 }
@@ -0,0 +1,113 @@
+use crate::cli::Paths;
+use crate::gallformersdb::{GallformersDB, PlaceSource, SpeciesPlaceSource};
+use crate::Res;
+use rusqlite::Connection;
+
+/// describes where a single range entry came from, e.g., `USDA snapshot 3 of the checklist downloaded 2021-10-18`
+pub fn describe(s: &SpeciesPlaceSource) -> String {
+    let source = match s.source {
+        Some(PlaceSource::Usda) => format!(
+            "USDA snapshot {} of the checklist downloaded {}",
+            s.version, s.asof
+        ),
+        Some(PlaceSource::Vascan) => format!("VASCAN API {} last updated {}", s.version, s.asof),
+        Some(PlaceSource::Manual) => "entered by a curator".to_string(),
+        None => return "no source recorded, it was entered by a curator".to_string(),
+    };
+    let source = match s.nativity {
+        Some(n) => format!("{}, {}", n, source),
+        None => source,
+    };
+    let source = match s.occurrence {
+        Some(o) => format!("{}, {}", o, source),
+        None => source,
+    };
+    if s.record.is_empty() {
+        format!("{}, recorded {}", source, s.recorded)
+    } else {
+        format!("{}, recorded {}, from {}", source, s.recorded, s.record)
+    }
+}
+
+/// prints where each of the places in a host's range came from
+pub fn provenance(paths: &Paths, host: &str) -> Res<()> {
+    let gf_c = Connection::open(&paths.gf_db)?;
+    let mut gf_db = GallformersDB::new(&gf_c);
+    if !gf_db.host_exists(host)? {
+        return Err(format!("{} is not a host in {:?}", host, paths.gf_db).into());
+    }
+    let sources = gf_db.select_place_sources(host)?;
+    if sources.is_empty() {
+        println!("{} has no range.", host);
+    }
+    for s in sources {
+        println!("{}: {}", s.place, describe(&s));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nativity::{Nativity, Occurrence};
+
+    #[test]
+    fn test_describe() {
+        let s = SpeciesPlaceSource {
+            place: "Minnesota".to_string(),
+            source: Some(PlaceSource::Usda),
+            version: "3".to_string(),
+            asof: "2021-10-18".to_string(),
+            recorded: "20211018T091300Z".to_string(),
+            record: "QUAL Quercus alba L.".to_string(),
+            nativity: None,
+            occurrence: None,
+        };
+        assert_eq!(
+            describe(&s),
+            "USDA snapshot 3 of the checklist downloaded 2021-10-18, recorded 20211018T091300Z, from QUAL Quercus alba L."
+        );
+        let native = SpeciesPlaceSource {
+            place: "Ontario".to_string(),
+            source: Some(PlaceSource::Vascan),
+            version: "0.1".to_string(),
+            asof: "2021-09-01".to_string(),
+            record: String::new(),
+            nativity: Some(Nativity::Native),
+            ..s
+        };
+        assert_eq!(
+            describe(&native),
+            "native, VASCAN API 0.1 last updated 2021-09-01, recorded 20211018T091300Z"
+        );
+        let manual = SpeciesPlaceSource {
+            source: Some(PlaceSource::Manual),
+            nativity: None,
+            ..native
+        };
+        assert_eq!(
+            describe(&manual),
+            "entered by a curator, recorded 20211018T091300Z"
+        );
+        let none = SpeciesPlaceSource {
+            source: None,
+            ..manual
+        };
+        assert!(describe(&none).starts_with("no source recorded"));
+        let doubtful = SpeciesPlaceSource {
+            source: Some(PlaceSource::Vascan),
+            version: "0.1".to_string(),
+            asof: "2021-09-01".to_string(),
+            occurrence: Some(Occurrence::Doubtful),
+            ..none
+        };
+        assert_eq!(
+            describe(&doubtful),
+            "doubtful, VASCAN API 0.1 last updated 2021-09-01, recorded 20211018T091300Z"
+        );
+        assert_eq!(
+            "vascan".parse::<PlaceSource>().unwrap(),
+            PlaceSource::Vascan
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::ImporterError;
+use crate::Res;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// a CSV record that could not be decoded and was set aside so that the rest of the import could carry on
+#[derive(Debug)]
+struct QuarantinedRecord {
+    file: String,
+    line: String,
+    error: String,
+    raw: Vec<u8>,
+}
+
+/// collects malformed CSV records during a lenient import.
+#[derive(Debug, Default)]
+pub struct Quarantine {
+    records: Vec<QuarantinedRecord>,
+    counts: BTreeMap<String, usize>,
+}
+
+impl Quarantine {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// quarantines the raw bytes of a record for the given region along with the error that it failed with.
+    pub fn add(&mut self, region: &str, error: &ImporterError, raw: &[u8]) {
+        let at = error.location();
+        // the file and line have their own columns so drop them from the message
+        let message = error.to_string();
+        let message = message
+            .strip_prefix(&at.to_string())
+            .unwrap_or(&message)
+            .to_string();
+        self.records.push(QuarantinedRecord {
+            file: at
+                .file
+                .as_ref()
+                .map(|f| f.display().to_string())
+                .unwrap_or_default(),
+            line: at.row.map(|r| r.to_string()).unwrap_or_default(),
+            error: message,
+            raw: raw.to_vec(),
+        });
+        *self.counts.entry(region.to_string()).or_insert(0) += 1;
+    }
+
+    /// writes all of the quarantined records to a CSV file. the raw column holds the record exactly as it was read,
+    /// invalid UTF-8 and all, so that it can be fixed and re-imported.
+    pub fn write_csv(&self, path: &Path) -> Res<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["file", "line", "error", "raw"])?;
+        for r in &self.records {
+            wtr.write_record([
+                r.file.as_bytes(),
+                r.line.as_bytes(),
+                r.error.as_bytes(),
+                r.raw.as_slice(),
+            ])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// prints the number of quarantined records for each region
+    pub fn print_summary(&self) {
+        println!("Quarantined {} malformed records:", self.records.len());
+        for (region, count) in &self.counts {
+            println!("  {}: {}", region, count);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::cli::{Paths, ReconcileOptions, Rollup};
+use crate::error::{ImporterError, Location, ResultExt};
+use crate::exporttogf::{resolve_host, Resolved};
+use crate::gallformersdb::GallformersDB;
+use crate::plantdb::PlantDB;
+use crate::species::SpeciesName;
+use crate::Res;
+use rusqlite::Connection;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// the most candidates that are offered for a host
+const MAX_CANDIDATES: usize = 5;
+/// how far apart two genera can be, in edits, for the names in one of them to be candidates for a host in the other
+const MAX_GENUS_DISTANCE: usize = 2;
+/// candidates that are further than this from the host, in edits once the epithet endings are normalised, are not
+/// offered
+const MAX_DISTANCE: usize = 2;
+
+/// Latin endings that change with the gender of the genus, longest first. each is replaced with the stem ending so
+/// that, e.g., `albus`, `alba` and `album` all normalise to `alb`, and `ruber` and `rubra` to `rubr`.
+const GENDER_ENDINGS: &[(&str, &str)] = &[
+    ("er", "r"),
+    ("ra", "r"),
+    ("rum", "r"),
+    ("us", ""),
+    ("um", ""),
+    ("is", ""),
+    ("a", ""),
+    ("e", ""),
+];
+
+/// a name in plants.db that an unmatched host might be meant to be
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    /// the number of edits between the host and the name once the epithet endings are normalised
+    pub distance: usize,
+    /// the number of edits between the host and the name as they are written
+    pub raw_distance: usize,
+}
+
+/// a row of the name map file
+#[derive(Debug, Deserialize, Serialize)]
+struct NameMapping {
+    /// the name of the host in gallformers
+    host: String,
+    /// the name in plants.db to use for the host
+    usda: String,
+}
+
+/// strips the ending of an epithet that depends on the gender of the genus
+pub fn normalise_epithet(epithet: &str) -> String {
+    let e = epithet.to_lowercase();
+    for (ending, stem) in GENDER_ENDINGS {
+        if e.len() > ending.len() + 2 && e.ends_with(ending) {
+            return format!("{}{}", &e[..e.len() - ending.len()], stem);
+        }
+    }
+    e
+}
+
+/// the name with the hybrid signs removed, each epithet normalised and the rank markers kept as they are
+fn normalise(name: &str) -> String {
+    name.split_whitespace()
+        .filter(|w| !["x", "X", "×"].contains(w))
+        .map(|w| w.trim_start_matches('×'))
+        .enumerate()
+        .map(|(n, w)| {
+            if n == 0 || w.ends_with('.') {
+                w.to_lowercase()
+            } else {
+                normalise_epithet(w)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the genus of a name without the hybrid sign of a nothogenus, which can be an ASCII x, e.g., `x Agropogon lutosus`
+fn genus(name: &str) -> &str {
+    let mut words = name.split_whitespace();
+    let first = match words.next() {
+        Some("x") | Some("X") => words.next(),
+        w => w,
+    };
+    first.unwrap_or_default().trim_start_matches('×')
+}
+
+/// the names that the host could be meant to be, closest first. `names` is keyed by genus.
+pub fn candidates(host: &str, names: &BTreeMap<String, Vec<String>>) -> Vec<Candidate> {
+    let host_genus = genus(host).to_lowercase();
+    let host_normal = normalise(host);
+    let mut found: Vec<Candidate> = names
+        .iter()
+        .filter(|(g, _)| strsim::levenshtein(&g.to_lowercase(), &host_genus) <= MAX_GENUS_DISTANCE)
+        .flat_map(|(_, ns)| ns)
+        .map(|n| Candidate {
+            name: n.clone(),
+            distance: strsim::levenshtein(&normalise(n), &host_normal),
+            raw_distance: strsim::levenshtein(n, host),
+        })
+        .filter(|c| c.distance <= MAX_DISTANCE)
+        .collect();
+    found.sort_by(|a, b| {
+        (a.distance, a.raw_distance, &a.name).cmp(&(b.distance, b.raw_distance, &b.name))
+    });
+    found.truncate(MAX_CANDIDATES);
+    found
+}
+
+/// reads the name map, host name -> plants.db name. a missing file is an empty map.
+pub fn read_name_map(path: &Path) -> Res<BTreeMap<String, String>> {
+    let mut map = BTreeMap::new();
+    if !path.is_file() {
+        return Ok(map);
+    }
+    let at = Location::file(path);
+    let mut rdr = csv::Reader::from_path(path).at(&at)?;
+    for m in rdr.deserialize() {
+        let m: NameMapping = m.at(&at)?;
+        map.insert(m.host, m.usda);
+    }
+    Ok(map)
+}
+
+pub fn write_name_map(path: &Path, map: &BTreeMap<String, String>) -> Res<()> {
+    let at = Location::file(path);
+    let mut wtr = csv::Writer::from_path(path).at(&at)?;
+    for (host, usda) in map {
+        wtr.serialize(NameMapping {
+            host: host.clone(),
+            usda: usda.clone(),
+        })
+        .at(&at)?;
+    }
+    wtr.flush().at(&at)?;
+    Ok(())
+}
+
+/// the name to look a host up by in plants.db, which is the mapped name if the host is in the name map
+pub fn mapped_name(host: &str, map: &BTreeMap<String, String>) -> Res<SpeciesName> {
+    let name = map.get(host).map(String::as_str).unwrap_or(host);
+    SpeciesName::new(name).map_err(|_| ImporterError::NameParse {
+        at: Default::default(),
+        name: name.to_string(),
+    })
+}
+
+/// finds candidates for all of the gallformers hosts that do not match anything in plants.db and adds the accepted
+/// ones to the name map
+pub fn reconcile(paths: &Paths, options: &ReconcileOptions) -> Res<()> {
+    let mut map = read_name_map(&paths.name_map)?;
+    let mut accepted = 0;
+    for a in &options.accept {
+        match a.split_once('=') {
+            Some((host, usda)) => {
+                map.insert(host.trim().to_string(), usda.trim().to_string());
+                accepted += 1;
+            }
+            None => return Err(format!("expected HOST=NAME but got {:?}", a).into()),
+        }
+    }
+
+    let gf_c = Connection::open(&paths.gf_db)?;
+    let mut gf_db = GallformersDB::new(&gf_c);
+    let plant_c = Connection::open(&paths.plants_db)?;
+    let mut plant_db = PlantDB::new(&plant_c);
+
+    let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for n in plant_db.select_plant_names()? {
+        names.entry(genus(&n).to_string()).or_default().push(n);
+    }
+
+    let mut hosts: Vec<String> = gf_db.select_all_plants()?.into_keys().collect();
+    hosts.sort();
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock().lines();
+    let mut unmatched = 0;
+    for host in hosts {
+        // a host is only matched if the export would find a range for it, through its synonym if it uses one
+        let matched = match mapped_name(&host, &map) {
+            Ok(name) => match resolve_host(&mut plant_db, name)? {
+                Resolved::Accepted(name) | Resolved::Synonym(name, _) => !plant_db
+                    .select_plant_regions(name, Rollup::Species)?
+                    .is_empty(),
+                Resolved::Ambiguous(_) => false,
+            },
+            Err(_) => false,
+        };
+        if matched {
+            continue;
+        }
+        unmatched += 1;
+
+        let found = candidates(&host, &names);
+        println!("No match for: {}", host);
+        if found.is_empty() {
+            println!("  no candidates");
+            continue;
+        }
+        for (n, c) in found.iter().enumerate() {
+            println!("  {}. {} (distance {})", n + 1, c.name, c.distance);
+        }
+        if options.interactive {
+            print!("  accept [1-{}], or enter to skip: ", found.len());
+            std::io::stdout().flush()?;
+            let choice = input.next().transpose()?.unwrap_or_default();
+            if let Some(c) = choice
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| found.get(n.wrapping_sub(1)))
+            {
+                map.insert(host.clone(), c.name.clone());
+                accepted += 1;
+            }
+        }
+    }
+
+    if accepted > 0 {
+        write_name_map(&paths.name_map, &map)?;
+        println!("Added {} names to {:?}.", accepted, paths.name_map);
+    }
+    println!("{} hosts had no match.", unmatched);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalise_epithet() {
+        assert_eq!(normalise_epithet("albus"), "alb");
+        assert_eq!(normalise_epithet("alba"), "alb");
+        assert_eq!(normalise_epithet("album"), "alb");
+        assert_eq!(normalise_epithet("ruber"), "rubr");
+        assert_eq!(normalise_epithet("rubra"), "rubr");
+        assert_eq!(normalise_epithet("rubrum"), "rubr");
+        assert_eq!(normalise_epithet("virginiana"), "virginian");
+        assert_eq!(normalise_epithet("virginianus"), "virginian");
+        assert_eq!(normalise_epithet("grandis"), "grand");
+        assert_eq!(normalise_epithet("grande"), "grand");
+        // too short to have an ending
+        assert_eq!(normalise_epithet("ana"), "ana");
+        assert_eq!(normalise("Quercus ×Alba"), "quercus alb");
+        assert_eq!(
+            normalise("Acer saccharum var. nigra"),
+            "acer sacchar var. nigr"
+        );
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for n in [
+            "Quercus alba",
+            "Quercus x leana",
+            "Quercus rubra",
+            "Quercus velutina",
+            "Quercus albicaulis",
+            "Acer rubrum",
+            "Acer saccharum var. nigrum",
+            "x Achnella caduca",
+            "x Agropogon littoralis",
+        ] {
+            names
+                .entry(genus(n).to_string())
+                .or_default()
+                .push(n.to_string());
+        }
+        let found = |host: &str| -> Vec<String> {
+            candidates(host, &names)
+                .into_iter()
+                .map(|c| c.name)
+                .collect()
+        };
+
+        assert_eq!(found("Quercus albus"), vec!["Quercus alba"]);
+        assert_eq!(found("Quercus ×leana"), vec!["Quercus x leana"]);
+        assert_eq!(found("Qurecus rubra"), vec!["Quercus rubra"]);
+        assert_eq!(found("Acer rubra"), vec!["Acer rubrum"]);
+        assert_eq!(
+            found("Acer sacharum var. nigrum"),
+            vec!["Acer saccharum var. nigrum"]
+        );
+        assert!(found("Betula papyrifera").is_empty());
+        assert_eq!(genus("x Agropogon lutosus"), "Agropogon");
+        assert_eq!(genus("×Agropogon lutosus"), "Agropogon");
+        assert_eq!(
+            found("x Agropogon litoralis"),
+            vec!["x Agropogon littoralis"]
+        );
+        assert!(names.contains_key("Achnella") && !names.contains_key("x"));
+
+        let ranked = candidates("Quercus albica", &names);
+        assert_eq!(ranked[0].name, "Quercus alba");
+        assert!(ranked.windows(2).all(|w| w[0].distance <= w[1].distance));
+    }
+
+    #[test]
+    fn test_name_map() {
+        // unique to the process so that concurrent test runs do not share the file
+        let mut p = std::env::temp_dir();
+        p.push(format!("importer_test_name_map_{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&p);
+        assert!(read_name_map(&p).unwrap().is_empty());
+
+        let mut map = BTreeMap::new();
+        map.insert("Quercus albus".to_string(), "Quercus alba".to_string());
+        write_name_map(&p, &map).unwrap();
+        let read = read_name_map(&p).unwrap();
+        std::fs::remove_file(&p).unwrap();
+        assert_eq!(read, map);
+
+        assert_eq!(mapped_name("Quercus albus", &map).unwrap().specific, "alba");
+        assert_eq!(
+            mapped_name("Quercus rubra", &map).unwrap().specific,
+            "rubra"
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use crate::Res;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// whether a row that the export wants to write is new or was already in the gallformers DB, or is one that a sync
+/// removes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Add,
+    Exists,
+    Remove,
+}
+
+impl Change {
+    pub fn from_inserted(inserted: bool) -> Self {
+        if inserted {
+            Change::Add
+        } else {
+            Change::Exists
+        }
+    }
+}
+
+/// how a region came to be in the range of a host
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionMatch {
+    /// the region is in the range of the taxon with exactly the host's name and rank
+    Exact,
+    /// the region is only in the range of infraspecific taxa of the host, which were rolled up into it
+    Rollup,
+}
+
+/// a single row of the CSV version of the report. which of the columns are filled in depends on the table.
+#[derive(Debug, Serialize)]
+struct ReportRow<'a> {
+    table: &'a str,
+    species: &'a str,
+    place: &'a str,
+    parent: &'a str,
+    change: Change,
+    /// only for speciesplace rows
+    #[serde(rename = "match")]
+    matched: Option<RegionMatch>,
+    /// only for aliasspecies rows
+    alias: &'a str,
+    alias_type: &'a str,
+    /// only for taxonomy and speciestaxonomy rows
+    taxonomy: &'a str,
+}
+
+#[derive(Debug, Default)]
+pub struct SpeciesReport {
+    pub places: BTreeMap<String, (Change, RegionMatch)>,
+    /// alias name -> type, e.g., scientific
+    pub aliases: BTreeMap<String, (String, Change)>,
+    pub genus: Option<(String, Change)>,
+    /// place -> the source that no longer asserts it and the other sources that still do. the speciesplace row is only
+    /// removed if there are none.
+    pub removed: BTreeMap<String, (String, Vec<String>)>,
+}
+
+/// collects all of the place, placeplace and speciesplace rows that an export wrote, or would write, to the
+/// gallformers DB so that they can be reviewed.
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub places: BTreeMap<String, Change>,
+    pub place_parents: BTreeMap<String, (String, Change)>,
+    pub families: BTreeMap<String, Change>,
+    /// genus -> the family it was put in
+    pub genera: BTreeMap<String, (String, Change)>,
+    /// genus -> the family it is in in gallformers, if any, and the family USDA puts it in
+    pub family_conflicts: BTreeMap<String, (Option<String>, String)>,
+    pub species: BTreeMap<String, SpeciesReport>,
+    pub unmatched: Vec<String>,
+    /// host -> the accepted taxon it was resolved to because the host's name is a synonym
+    pub synonyms: BTreeMap<String, String>,
+    /// host -> the accepted taxa the host's name is a synonym of, when there is more than one
+    pub ambiguous: BTreeMap<String, Vec<String>>,
+}
+
+impl ExportReport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn place(&mut self, place: &str, change: Change) {
+        self.places.insert(place.to_string(), change);
+    }
+
+    pub fn place_parent(&mut self, place: &str, parent: &str, change: Change) {
+        self.place_parents
+            .insert(place.to_string(), (parent.to_string(), change));
+    }
+
+    pub fn species_place(
+        &mut self,
+        species: &str,
+        place: &str,
+        change: Change,
+        matched: RegionMatch,
+    ) {
+        self.species
+            .entry(species.to_string())
+            .or_default()
+            .places
+            .insert(place.to_string(), (change, matched));
+    }
+
+    pub fn alias(&mut self, species: &str, alias: &str, alias_type: &str, change: Change) {
+        self.species
+            .entry(species.to_string())
+            .or_default()
+            .aliases
+            .insert(alias.to_string(), (alias_type.to_string(), change));
+    }
+
+    pub fn species_place_removed(
+        &mut self,
+        species: &str,
+        place: &str,
+        source: &str,
+        remaining: Vec<String>,
+    ) {
+        self.species
+            .entry(species.to_string())
+            .or_default()
+            .removed
+            .insert(place.to_string(), (source.to_string(), remaining));
+    }
+
+    pub fn family(&mut self, family: &str, change: Change) {
+        self.families.insert(family.to_string(), change);
+    }
+
+    pub fn genus(&mut self, genus: &str, family: &str, change: Change) {
+        self.genera
+            .insert(genus.to_string(), (family.to_string(), change));
+    }
+
+    pub fn family_conflict(&mut self, genus: &str, gf_family: Option<String>, usda_family: &str) {
+        self.family_conflicts
+            .insert(genus.to_string(), (gf_family, usda_family.to_string()));
+    }
+
+    pub fn species_genus(&mut self, species: &str, genus: &str, change: Change) {
+        self.species.entry(species.to_string()).or_default().genus =
+            Some((genus.to_string(), change));
+    }
+
+    pub fn unmatched(&mut self, species: &str) {
+        self.unmatched.push(species.to_string());
+    }
+
+    pub fn synonym(&mut self, species: &str, accepted: &str) {
+        self.synonyms
+            .insert(species.to_string(), accepted.to_string());
+    }
+
+    pub fn ambiguous(&mut self, species: &str, accepted: Vec<String>) {
+        self.ambiguous.insert(species.to_string(), accepted);
+    }
+
+    fn count<'a>(changes: impl Iterator<Item = &'a Change>, change: Change) -> usize {
+        changes.filter(|c| **c == change).count()
+    }
+
+    /// prints a short summary of the number of rows added, along with every link that a sync removed
+    pub fn print_summary(&self) {
+        self.summarise(true);
+    }
+
+    /// `list_removed` is false when the removals were already listed with the rest of their host's rows
+    fn summarise(&self, list_removed: bool) {
+        println!(
+            "places: {} added, {} existing",
+            Self::count(self.places.values(), Change::Add),
+            Self::count(self.places.values(), Change::Exists)
+        );
+        println!(
+            "placeplace: {} added, {} existing",
+            Self::count(self.place_parents.values().map(|(_, c)| c), Change::Add),
+            Self::count(self.place_parents.values().map(|(_, c)| c), Change::Exists)
+        );
+        let sp = || self.species.values().flat_map(|s| s.places.values());
+        println!(
+            "speciesplace: {} added, {} existing, {} rolled up from infraspecific taxa",
+            Self::count(sp().map(|(c, _)| c), Change::Add),
+            Self::count(sp().map(|(c, _)| c), Change::Exists),
+            sp().filter(|(_, m)| *m == RegionMatch::Rollup).count()
+        );
+        let rm = || {
+            self.species
+                .values()
+                .flat_map(|s| s.removed.values().map(|(_, r)| r))
+        };
+        if rm().next().is_some() {
+            println!(
+                "speciesplace: {} removed, {} kept as other sources assert them",
+                rm().filter(|r| r.is_empty()).count(),
+                rm().filter(|r| !r.is_empty()).count()
+            );
+        }
+        let al = || {
+            self.species
+                .values()
+                .flat_map(|s| s.aliases.values().map(|(_, c)| c))
+        };
+        println!(
+            "aliasspecies: {} added, {} existing",
+            Self::count(al(), Change::Add),
+            Self::count(al(), Change::Exists)
+        );
+        println!(
+            "taxonomy: {} families added, {} genera added, {} genera existing",
+            Self::count(self.families.values(), Change::Add),
+            Self::count(self.genera.values().map(|(_, c)| c), Change::Add),
+            Self::count(self.genera.values().map(|(_, c)| c), Change::Exists)
+        );
+        let st = || {
+            self.species
+                .values()
+                .filter_map(|s| s.genus.as_ref().map(|(_, c)| c))
+        };
+        println!(
+            "speciestaxonomy: {} added, {} existing",
+            Self::count(st(), Change::Add),
+            Self::count(st(), Change::Exists)
+        );
+        // removals lose data so they are always listed for review
+        if list_removed {
+            for (species, report) in &self.species {
+                for (place, (source, remaining)) in &report.removed {
+                    if remaining.is_empty() {
+                        println!("Removed: {} from {}", species, place);
+                    } else {
+                        println!(
+                            "Removed: the {} source of {} in {}, kept for {}",
+                            source,
+                            species,
+                            place,
+                            remaining.join(", ")
+                        );
+                    }
+                }
+            }
+        }
+        // conflicts need fixing by hand so they are always listed
+        for (genus, (gf_family, usda_family)) in &self.family_conflicts {
+            println!(
+                "Family conflict: {} is in {} in gallformers but in {} in USDA, its hosts were not linked to it",
+                genus,
+                gf_family.as_deref().unwrap_or("no family"),
+                usda_family
+            );
+        }
+        println!("species resolved from a synonym: {}", self.synonyms.len());
+        println!(
+            "species with no match: {}",
+            self.unmatched.len() + self.ambiguous.len()
+        );
+    }
+
+    /// prints the full per-species report. new rows are marked with a `+` and existing ones with a `=`.
+    pub fn print(&self) {
+        let mark = |c: &Change| match c {
+            Change::Add => "+",
+            Change::Exists => "=",
+            Change::Remove => "-",
+        };
+        for (place, change) in &self.places {
+            if *change == Change::Add {
+                println!("{} place {}", mark(change), place);
+            }
+        }
+        for (place, (parent, change)) in &self.place_parents {
+            if *change == Change::Add {
+                println!("{} placeplace {} -> {}", mark(change), place, parent);
+            }
+        }
+        for (family, change) in &self.families {
+            if *change == Change::Add {
+                println!("{} family {}", mark(change), family);
+            }
+        }
+        for (genus, (family, change)) in &self.genera {
+            if *change == Change::Add {
+                println!("{} genus {} -> {}", mark(change), genus, family);
+            }
+        }
+        for (species, report) in &self.species {
+            match self.synonyms.get(species) {
+                Some(accepted) => println!("{} (a synonym of {})", species, accepted),
+                None => println!("{}", species),
+            }
+            for (place, (change, matched)) in &report.places {
+                match matched {
+                    RegionMatch::Exact => println!("  {} {}", mark(change), place),
+                    RegionMatch::Rollup => println!("  {} {} (rolled up)", mark(change), place),
+                }
+            }
+            for (alias, (alias_type, change)) in &report.aliases {
+                println!("  {} {} alias {}", mark(change), alias_type, alias);
+            }
+            if let Some((genus, change)) = &report.genus {
+                println!("  {} genus {}", mark(change), genus);
+            }
+            for (place, (source, remaining)) in &report.removed {
+                if remaining.is_empty() {
+                    println!("  {} {}", mark(&Change::Remove), place);
+                } else {
+                    println!(
+                        "  {} {} (only the {} source, kept for {})",
+                        mark(&Change::Remove),
+                        place,
+                        source,
+                        remaining.join(", ")
+                    );
+                }
+            }
+        }
+        for species in &self.unmatched {
+            println!("No match for: {}", species);
+        }
+        for (species, accepted) in &self.ambiguous {
+            println!(
+                "No match for: {}, it is a synonym of more than one taxon: {}",
+                species,
+                accepted.join(", ")
+            );
+        }
+        self.summarise(false);
+    }
+
+    /// writes the report as CSV, one row per place, placeplace, speciesplace, aliasspecies, taxonomy or speciestaxonomy
+    /// row.
+    pub fn write_csv(&self, path: &Path) -> Res<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        for (place, change) in &self.places {
+            wtr.serialize(ReportRow {
+                table: "place",
+                species: "",
+                place,
+                parent: "",
+                change: *change,
+                matched: None,
+                alias: "",
+                alias_type: "",
+                taxonomy: "",
+            })?;
+        }
+        for (place, (parent, change)) in &self.place_parents {
+            wtr.serialize(ReportRow {
+                table: "placeplace",
+                species: "",
+                place,
+                parent,
+                change: *change,
+                matched: None,
+                alias: "",
+                alias_type: "",
+                taxonomy: "",
+            })?;
+        }
+        for (family, change) in &self.families {
+            wtr.serialize(ReportRow {
+                table: "taxonomy",
+                species: "",
+                place: "",
+                parent: "",
+                change: *change,
+                matched: None,
+                alias: "",
+                alias_type: "",
+                taxonomy: family,
+            })?;
+        }
+        for (genus, (family, change)) in &self.genera {
+            wtr.serialize(ReportRow {
+                table: "taxonomy",
+                species: "",
+                place: "",
+                parent: family,
+                change: *change,
+                matched: None,
+                alias: "",
+                alias_type: "",
+                taxonomy: genus,
+            })?;
+        }
+        for (species, report) in &self.species {
+            for (place, (change, matched)) in &report.places {
+                wtr.serialize(ReportRow {
+                    table: "speciesplace",
+                    species,
+                    place,
+                    parent: "",
+                    change: *change,
+                    matched: Some(*matched),
+                    alias: "",
+                    alias_type: "",
+                    taxonomy: "",
+                })?;
+            }
+            for (alias, (alias_type, change)) in &report.aliases {
+                wtr.serialize(ReportRow {
+                    table: "aliasspecies",
+                    species,
+                    place: "",
+                    parent: "",
+                    change: *change,
+                    matched: None,
+                    alias,
+                    alias_type,
+                    taxonomy: "",
+                })?;
+            }
+            for (place, (_, remaining)) in &report.removed {
+                wtr.serialize(ReportRow {
+                    table: if remaining.is_empty() {
+                        "speciesplace"
+                    } else {
+                        "speciesplacesource"
+                    },
+                    species,
+                    place,
+                    parent: "",
+                    change: Change::Remove,
+                    matched: None,
+                    alias: "",
+                    alias_type: "",
+                    taxonomy: "",
+                })?;
+            }
+            if let Some((genus, change)) = &report.genus {
+                wtr.serialize(ReportRow {
+                    table: "speciestaxonomy",
+                    species,
+                    place: "",
+                    parent: "",
+                    change: *change,
+                    matched: None,
+                    alias: "",
+                    alias_type: "",
+                    taxonomy: genus,
+                })?;
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_csv() {
+        let mut report = ExportReport::new();
+        report.place("Alabama", Change::Add);
+        report.place_parent("Alabama", "United States", Change::Add);
+        report.species_place("Quercus alba", "Alabama", Change::Add, RegionMatch::Exact);
+        report.species_place(
+            "Quercus alba",
+            "Arkansas",
+            Change::Exists,
+            RegionMatch::Rollup,
+        );
+        report.alias("Quercus alba", "Quercus candida", "scientific", Change::Add);
+        report.family("Fagaceae", Change::Add);
+        report.genus("Quercus", "Fagaceae", Change::Add);
+        report.species_genus("Quercus alba", "Quercus", Change::Add);
+        report.species_place_removed("Quercus alba", "Iowa", "usda", vec![]);
+        report.species_place_removed(
+            "Quercus alba",
+            "Ontario",
+            "usda",
+            vec!["vascan".to_string()],
+        );
+
+        let mut p = std::env::temp_dir();
+        p.push("importer_test_report.csv");
+        report.write_csv(&p).unwrap();
+        let csv = std::fs::read_to_string(&p).unwrap();
+        std::fs::remove_file(&p).unwrap();
+
+        assert_eq!(
+            csv,
+            "table,species,place,parent,change,match,alias,alias_type,taxonomy\n\
+             place,,Alabama,,add,,,,\n\
+             placeplace,,Alabama,United States,add,,,,\n\
+             taxonomy,,,,add,,,,Fagaceae\n\
+             taxonomy,,,Fagaceae,add,,,,Quercus\n\
+             speciesplace,Quercus alba,Alabama,,add,exact,,,\n\
+             speciesplace,Quercus alba,Arkansas,,exists,rollup,,,\n\
+             aliasspecies,Quercus alba,,,add,,Quercus candida,scientific,\n\
+             speciesplace,Quercus alba,Iowa,,remove,,,,\n\
+             speciesplacesource,Quercus alba,Ontario,,remove,,,,\n\
+             speciestaxonomy,Quercus alba,,,add,,,,Quercus\n"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use crate::cli::Paths;
+use crate::plant::PlantName;
+use crate::plantdb::PlantDB;
+use crate::Res;
+use rusqlite::Connection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// an import of the USDA checklist
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub id: i64,
+    pub imported: String,
+    pub downloaded: String,
+}
+
+/// a file that was imported as part of a snapshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotFile {
+    pub region_code: String,
+    pub filename: String,
+    pub sha256: String,
+    pub rows: i64,
+}
+
+/// a single row from one of the checklist CSVs as it was imported in a snapshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotRecord {
+    pub region_code: String,
+    pub symbol: String,
+    pub symbol_syn: String,
+    pub name: String,
+    pub family: String,
+}
+
+/// the differences between two snapshots of the checklist. taxa are keyed by their USDA symbol.
+#[derive(Debug, Default, PartialEq)]
+pub struct ChecklistDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// (old name, new name) for taxa whose name changed other than the authority
+    pub renamed: Vec<(String, String)>,
+    /// (old name, new name) for taxa where only the authority changed
+    pub authority_changed: Vec<(String, String)>,
+    /// (accepted name, synonym) pairs
+    pub synonyms_added: Vec<(String, String)>,
+    pub synonyms_removed: Vec<(String, String)>,
+    /// accepted name -> (regions gained, regions lost)
+    pub distribution: BTreeMap<String, (Vec<String>, Vec<String>)>,
+}
+
+/// the parts of a checklist that are compared between snapshots
+#[derive(Default)]
+struct Checklist {
+    accepted: BTreeMap<String, String>,
+    synonyms: BTreeSet<(String, String)>,
+    distribution: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Checklist {
+    fn new(records: &[SnapshotRecord], regions: &BTreeSet<String>) -> Self {
+        let mut c = Checklist::default();
+        for r in records.iter().filter(|r| regions.contains(&r.region_code)) {
+            if r.symbol_syn.is_empty() {
+                c.accepted.insert(r.symbol.clone(), r.name.clone());
+                c.distribution
+                    .entry(r.symbol.clone())
+                    .or_default()
+                    .insert(r.region_code.clone());
+            } else {
+                c.synonyms.insert((r.symbol.clone(), r.name.clone()));
+            }
+        }
+        c
+    }
+
+    fn name(&self, symbol: &str) -> String {
+        self.accepted
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| symbol.to_string())
+    }
+}
+
+/// true if the two names only differ by their authors
+fn same_taxon_name(a: &str, b: &str) -> bool {
+    match (PlantName::new(a.to_string()), PlantName::new(b.to_string())) {
+        (Ok(a), Ok(b)) => {
+            a.genus == b.genus
+                && a.specific == b.specific
+                && a.species_type == b.species_type
+                && a.sspvar == b.sspvar
+                && a.ranks.len() == b.ranks.len()
+                && a.ranks
+                    .iter()
+                    .zip(&b.ranks)
+                    .all(|(x, y)| x.rank == y.rank && x.epithet == y.epithet)
+        }
+        _ => false,
+    }
+}
+
+/// true if the two names have the same authors, even if they are written differently, e.g., with an `ex` author or a
+/// qualifier added, see `PlantName::same_authors`
+fn same_authors(a: &str, b: &str) -> bool {
+    match (PlantName::new(a.to_string()), PlantName::new(b.to_string())) {
+        (Ok(a), Ok(b)) => a.same_authors(&b),
+        _ => false,
+    }
+}
+
+/// compares two snapshots. only the given regions are compared, so that a snapshot that only re-imported some states
+/// does not look like it lost every other state.
+pub fn diff(
+    old: &[SnapshotRecord],
+    new: &[SnapshotRecord],
+    regions: &BTreeSet<String>,
+) -> ChecklistDiff {
+    let old = Checklist::new(old, regions);
+    let new = Checklist::new(new, regions);
+    let mut d = ChecklistDiff::default();
+
+    for (symbol, name) in &new.accepted {
+        match old.accepted.get(symbol) {
+            None => d.added.push(name.clone()),
+            Some(old_name) if old_name == name => {}
+            // a name that is only written differently has not changed its authority
+            Some(old_name) if same_taxon_name(old_name, name) => {
+                if !same_authors(old_name, name) {
+                    d.authority_changed.push((old_name.clone(), name.clone()))
+                }
+            }
+            Some(old_name) => d.renamed.push((old_name.clone(), name.clone())),
+        }
+    }
+    for (symbol, name) in &old.accepted {
+        if !new.accepted.contains_key(symbol) {
+            d.removed.push(name.clone());
+        }
+    }
+
+    for (symbol, syn) in new.synonyms.difference(&old.synonyms) {
+        d.synonyms_added.push((new.name(symbol), syn.clone()));
+    }
+    for (symbol, syn) in old.synonyms.difference(&new.synonyms) {
+        d.synonyms_removed.push((old.name(symbol), syn.clone()));
+    }
+
+    let empty = BTreeSet::new();
+    let symbols: BTreeSet<&String> = old
+        .distribution
+        .keys()
+        .chain(new.distribution.keys())
+        .collect();
+    for symbol in symbols {
+        let o = old.distribution.get(symbol).unwrap_or(&empty);
+        let n = new.distribution.get(symbol).unwrap_or(&empty);
+        let gained: Vec<String> = n.difference(o).cloned().collect();
+        let lost: Vec<String> = o.difference(n).cloned().collect();
+        if !gained.is_empty() || !lost.is_empty() {
+            let name = if new.accepted.contains_key(symbol) {
+                new.name(symbol)
+            } else {
+                old.name(symbol)
+            };
+            d.distribution.insert(name, (gained, lost));
+        }
+    }
+
+    d
+}
+
+impl ChecklistDiff {
+    pub fn print(&self) {
+        for n in &self.added {
+            println!("+ taxon {}", n);
+        }
+        for n in &self.removed {
+            println!("- taxon {}", n);
+        }
+        for (o, n) in &self.renamed {
+            println!("~ renamed {} -> {}", o, n);
+        }
+        for (o, n) in &self.authority_changed {
+            println!("~ authority {} -> {}", o, n);
+        }
+        for (a, s) in &self.synonyms_added {
+            println!("+ synonym {} of {}", s, a);
+        }
+        for (a, s) in &self.synonyms_removed {
+            println!("- synonym {} of {}", s, a);
+        }
+        for (name, (gained, lost)) in &self.distribution {
+            let mut changes: Vec<String> = gained.iter().map(|r| format!("+{}", r)).collect();
+            changes.extend(lost.iter().map(|r| format!("-{}", r)));
+            println!("~ range {}: {}", name, changes.join(" "));
+        }
+        println!(
+            "taxa: {} added, {} removed, {} renamed, {} authorities changed",
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len(),
+            self.authority_changed.len()
+        );
+        println!(
+            "synonyms: {} added, {} removed",
+            self.synonyms_added.len(),
+            self.synonyms_removed.len()
+        );
+        println!("ranges changed: {}", self.distribution.len());
+    }
+}
+
+/// lists all of the snapshots in the plants DB
+pub fn list_snapshots(paths: &Paths) -> Res<()> {
+    let plant_c = Connection::open(&paths.plants_db)?;
+    let mut plant_db = PlantDB::new(&plant_c);
+    for s in plant_db.select_snapshots()? {
+        let files = plant_db.select_snapshot_files(s.id)?;
+        let rows: i64 = files.iter().map(|f| f.rows).sum();
+        println!(
+            "{}: imported {}, downloaded {}, {} files, {} rows",
+            s.id,
+            s.imported,
+            s.downloaded,
+            files.len(),
+            rows
+        );
+    }
+    Ok(())
+}
+
+/// deletes the raw records of all but the `keep` most recent snapshots of each region to keep plants.db from growing
+/// with every import
+pub fn prune_snapshots(paths: &Paths, keep: usize) -> Res<()> {
+    let mut plant_c = Connection::open(&paths.plants_db)?;
+    let tx = plant_c.transaction()?;
+    let deleted = PlantDB::new(&tx).prune_snapshot_records(keep)?;
+    tx.commit()?;
+    // the space is only given back to the file system by a vacuum
+    plant_c.execute_batch("VACUUM;")?;
+    println!(
+        "Deleted {} records from the snapshots older than the latest {} of each region.",
+        deleted, keep
+    );
+    Ok(())
+}
+
+/// compares two snapshots, defaulting to the two most recent ones
+pub fn diff_snapshots(paths: &Paths, old: Option<i64>, new: Option<i64>) -> Res<()> {
+    let plant_c = Connection::open(&paths.plants_db)?;
+    let mut plant_db = PlantDB::new(&plant_c);
+    let snapshots = plant_db.select_snapshots()?;
+    let latest = |n: usize| snapshots.iter().rev().nth(n).map(|s| s.id);
+    let (old, new) = match (old, new) {
+        (Some(o), Some(n)) => (o, n),
+        (Some(o), None) => (o, latest(0).ok_or("There are no snapshots.")?),
+        (None, _) => (
+            latest(1).ok_or("There are fewer than two snapshots to compare.")?,
+            latest(0).ok_or("There are no snapshots.")?,
+        ),
+    };
+
+    let old_files = plant_db.select_snapshot_files(old)?;
+    let new_files = plant_db.select_snapshot_files(new)?;
+    if old_files.is_empty() || new_files.is_empty() {
+        return Err(format!("Snapshot {} or {} does not exist.", old, new).into());
+    }
+    let old_regions: BTreeSet<String> = old_files.iter().map(|f| f.region_code.clone()).collect();
+    let new_regions: BTreeSet<String> = new_files.iter().map(|f| f.region_code.clone()).collect();
+    // a region whose records were pruned from either snapshot can not be compared
+    let old_records = plant_db.select_snapshot_record_regions(old)?;
+    let new_records = plant_db.select_snapshot_record_regions(new)?;
+    let pruned: BTreeSet<&String> = old_regions
+        .intersection(&new_regions)
+        .filter(|r| !old_records.contains(*r) || !new_records.contains(*r))
+        .collect();
+    let regions: BTreeSet<String> = old_regions
+        .intersection(&new_regions)
+        .filter(|r| !pruned.contains(r))
+        .cloned()
+        .collect();
+    let changed: Vec<&String> = old_files
+        .iter()
+        .filter(|o| {
+            new_files
+                .iter()
+                .any(|n| n.region_code == o.region_code && n.sha256 != o.sha256)
+        })
+        .map(|f| &f.region_code)
+        .collect();
+
+    println!("Comparing snapshot {} to snapshot {}.", old, new);
+    println!(
+        "{} regions in common, {} of them with changed files: {:?}",
+        regions.len(),
+        changed.len(),
+        changed
+    );
+    let only: Vec<&String> = old_regions.symmetric_difference(&new_regions).collect();
+    if !only.is_empty() {
+        println!("Not compared, only in one of the snapshots: {:?}", only);
+    }
+    if !pruned.is_empty() {
+        println!("Not compared, the records were pruned: {:?}", pruned);
+    }
+
+    let d = diff(
+        &plant_db.select_snapshot_records(old)?,
+        &plant_db.select_snapshot_records(new)?,
+        &regions,
+    );
+    d.print();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(region: &str, symbol: &str, syn: &str, name: &str) -> SnapshotRecord {
+        SnapshotRecord {
+            region_code: region.to_string(),
+            symbol: symbol.to_string(),
+            symbol_syn: syn.to_string(),
+            name: name.to_string(),
+            family: "Fagaceae".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![
+            rec("MN", "QUAL", "", "Quercus alba L."),
+            rec("WI", "QUAL", "", "Quercus alba L."),
+            rec("MN", "QURU", "", "Quercus rubra L."),
+            rec("MN", "QUBI", "", "Quercus bicolor Willd."),
+            rec("MN", "QUMA2", "", "Quercus macrocarpa Michx."),
+            rec("MN", "QUCO2", "", "Quercus coccinea Münchh."),
+            rec("MN", "QURU", "QUBO2", "Quercus borealis F. Michx."),
+            rec("DC", "QUPH", "", "Quercus phellos L."),
+        ];
+        let new = vec![
+            rec("MN", "QUAL", "", "Quercus alba L."),
+            rec("MN", "QURU", "", "Quercus rubra Du Roi"),
+            rec("WI", "QURU", "", "Quercus rubra Du Roi"),
+            rec("MN", "QUBI", "", "Quercus bicolor Michx."),
+            rec("MN", "QUMA2", "", "Quercus macrocarpa Michx. var. foo Bar"),
+            rec("MN", "QUEL", "", "Quercus ellipsoidalis E.J. Hill"),
+            // only ascribed to another author, which is not a change of authority
+            rec("MN", "QUCO2", "", "Quercus coccinea Wangenh. ex Münchh."),
+            rec("MN", "QUEL", "QUPA", "Quercus palustris auct. non Münchh."),
+        ];
+        let regions: BTreeSet<String> = ["MN", "WI"].iter().map(|s| s.to_string()).collect();
+
+        let d = diff(&old, &new, &regions);
+        assert_eq!(d.added, vec!["Quercus ellipsoidalis E.J. Hill"]);
+        assert!(d.removed.is_empty());
+        assert_eq!(
+            d.renamed,
+            vec![(
+                "Quercus macrocarpa Michx.".to_string(),
+                "Quercus macrocarpa Michx. var. foo Bar".to_string()
+            )]
+        );
+        assert_eq!(
+            d.authority_changed,
+            vec![
+                (
+                    "Quercus bicolor Willd.".to_string(),
+                    "Quercus bicolor Michx.".to_string()
+                ),
+                (
+                    "Quercus rubra L.".to_string(),
+                    "Quercus rubra Du Roi".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            d.synonyms_added,
+            vec![(
+                "Quercus ellipsoidalis E.J. Hill".to_string(),
+                "Quercus palustris auct. non Münchh.".to_string()
+            )]
+        );
+        assert_eq!(
+            d.synonyms_removed,
+            vec![(
+                "Quercus rubra L.".to_string(),
+                "Quercus borealis F. Michx.".to_string()
+            )]
+        );
+        assert_eq!(
+            d.distribution.get("Quercus alba L."),
+            Some(&(vec![], vec!["WI".to_string()]))
+        );
+        assert_eq!(
+            d.distribution.get("Quercus rubra Du Roi"),
+            Some(&(vec!["WI".to_string()], vec![]))
+        );
+        // DC is not in both snapshots so it is not compared
+        assert!(!d.distribution.contains_key("Quercus phellos L."));
+    }
+}
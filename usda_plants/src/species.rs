@@ -1,3 +1,5 @@
+use crate::plant::{plant_name_to_species_name, PlantName, SpeciesType};
+
 #[derive(Debug)]
 pub struct Species {
     pub id: i64,
@@ -7,32 +9,17 @@ pub struct Species {
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct SpeciesName {
     pub genus: String,
+    /// empty for a name that is only a genus, e.g., `Quercus sp.`
     pub specific: String,
+    pub rank: SpeciesType,
     pub ssp: Option<String>,
     pub hybrid: bool,
 }
 
 impl SpeciesName {
-    pub fn new(name: String) -> Self {
-        let splits = name.split_whitespace().collect::<Vec<&str>>();
-        let g = splits[0];
-        let mut sp = splits[1];
-        let mut hybrid = false;
-        if sp == "x" || sp == "X" {
-            hybrid = true;
-            sp = splits[2];
-        }
-        let ssp = match splits.len() {
-            3 if !hybrid => Some(splits[2].to_string()),
-            _ => None,
-        };
-
-        SpeciesName {
-            genus: g.to_string(),
-            specific: sp.to_string(),
-            ssp,
-            hybrid,
-        }
+    /// parses a gallformers name with the same grammar as the USDA names, allowing `x` for the hybrid sign
+    pub fn new(name: &str) -> Result<Self, nom::error::Error<String>> {
+        PlantName::parse_tolerant(name).map(|n| plant_name_to_species_name(&n))
     }
 
     pub fn species_name(&mut self) -> String {
@@ -43,30 +30,61 @@ impl SpeciesName {
 #[test]
 fn test_to_name() {
     assert_eq!(
-        SpeciesName::new("Foo bar".to_string()),
-        SpeciesName {
+        SpeciesName::new("Foo bar"),
+        Ok(SpeciesName {
             genus: "Foo".to_string(),
             specific: "bar".to_string(),
+            rank: SpeciesType::Species,
             ssp: None,
             hybrid: false
-        }
+        })
     );
+    for name in ["Foo x bar", "Foo X bar", "Foo ×bar", "Foo × bar"] {
+        assert_eq!(
+            SpeciesName::new(name),
+            Ok(SpeciesName {
+                genus: "Foo".to_string(),
+                specific: "bar".to_string(),
+                rank: SpeciesType::Hybrid,
+                ssp: None,
+                hybrid: true
+            })
+        );
+    }
     assert_eq!(
-        SpeciesName::new("Foo x bar".to_string()),
-        SpeciesName {
-            genus: "Foo".to_string(),
-            specific: "bar".to_string(),
-            ssp: None,
-            hybrid: true
-        }
+        SpeciesName::new("Acer saccharum var. nigrum"),
+        Ok(SpeciesName {
+            genus: "Acer".to_string(),
+            specific: "saccharum".to_string(),
+            rank: SpeciesType::Variety,
+            ssp: Some("nigrum".to_string()),
+            hybrid: false
+        })
     );
     assert_eq!(
-        SpeciesName::new("Foo bar baz".to_string()),
-        SpeciesName {
-            genus: "Foo".to_string(),
-            specific: "bar".to_string(),
-            ssp: Some("baz".to_string()),
+        SpeciesName::new("Vitis riparia ssp. foo"),
+        Ok(SpeciesName {
+            genus: "Vitis".to_string(),
+            specific: "riparia".to_string(),
+            rank: SpeciesType::Subspecies,
+            ssp: Some("foo".to_string()),
             hybrid: false
-        }
+        })
+    );
+    // a host that is only a genus used to panic
+    assert_eq!(
+        SpeciesName::new("Quercus"),
+        Ok(SpeciesName {
+            genus: "Quercus".to_string(),
+            specific: "".to_string(),
+            rank: SpeciesType::Species,
+            ssp: None,
+            hybrid: false
+        })
+    );
+    assert_eq!(
+        SpeciesName::new("Quercus sp.").map(|n| n.specific),
+        Ok("".to_string())
     );
+    assert!(SpeciesName::new("").is_err());
 }
@@ -1,4 +1,4 @@
-pub type Err = Box<dyn std::error::Error>;
+pub type Err = crate::error::ImporterError;
 pub type Res<T> = Result<T, Err>;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -8,3 +8,51 @@ pub struct Region {
     pub code: String,
     pub typ: String,
 }
+
+/// the current UTC time as a compact ISO 8601 timestamp, e.g., `20211018T091300Z`. it sorts correctly as a string and
+/// is safe to use in file names.
+pub fn timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_timestamp(secs)
+}
+
+/// formats seconds since the UNIX epoch as a compact ISO 8601 UTC timestamp.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+// converts days since the UNIX epoch to a (year, month, day) date in the proleptic Gregorian calendar.
+// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+#[test]
+fn test_format_timestamp() {
+    assert_eq!(format_timestamp(0), "19700101T000000Z");
+    assert_eq!(format_timestamp(951782400), "20000229T000000Z");
+    assert_eq!(format_timestamp(1634548380), "20211018T091300Z");
+}
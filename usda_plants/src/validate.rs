@@ -0,0 +1,369 @@
+use crate::cli::Paths;
+use crate::error::{ImporterError, Location, ResultExt};
+use crate::plantdb::PlantDB;
+use crate::Res;
+use rusqlite::Connection;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// a file with fewer rows than this fraction of the rows in the last import of the same state looks truncated
+const MIN_FRACTION_OF_PREVIOUS: f64 = 0.9;
+/// a file with fewer rows than this fraction of the median rows across all of the states looks truncated
+const MIN_FRACTION_OF_MEDIAN: f64 = 0.5;
+
+/// what a single state's CSV looks like, as far as spotting missing data goes
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvStats {
+    pub region_code: String,
+    pub file: PathBuf,
+    pub rows: usize,
+    /// the last scientific name in the file. the checklists are sorted by name so this shows how far through the
+    /// alphabet the file gets.
+    pub last_name: String,
+    /// the first letters of all of the scientific names in the file
+    pub initials: BTreeSet<char>,
+}
+
+/// a reason to think that the data for a state is incomplete
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// the region is in regions.json but there is no CSV for it in the data directory
+    MissingCsv { name: String },
+    /// the file has a lot fewer rows than expected
+    FewRows {
+        rows: usize,
+        expected: usize,
+        compared_to: &'static str,
+    },
+    /// the file stops before the point in the alphabet that most of the other states reach
+    EndsEarly { last_name: String, expected: char },
+    /// names starting with these letters are in every other state but not this one
+    MissingInitials(Vec<char>),
+}
+
+impl Issue {
+    /// true if the data that would be imported for the state is incomplete. a state without a CSV does not get any
+    /// data imported so it is only worth a warning.
+    pub fn is_incomplete(&self) -> bool {
+        !matches!(self, Issue::MissingCsv { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingCsv { name } => write!(f, "{} is in the regions but has no CSV", name),
+            Issue::FewRows {
+                rows,
+                expected,
+                compared_to,
+            } => write!(
+                f,
+                "has {} rows but {} has {}, the file may be truncated",
+                rows, compared_to, expected
+            ),
+            Issue::EndsEarly {
+                last_name,
+                expected,
+            } => write!(
+                f,
+                "ends at {:?} but most states get as far as {}, the file may be truncated",
+                last_name, expected
+            ),
+            Issue::MissingInitials(initials) => write!(
+                f,
+                "has no names starting with {} though every other state does",
+                initials
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// reads the parts of a CSV that are needed to check whether it is complete. malformed records are counted but are
+/// otherwise left for the import to deal with.
+pub fn scan_csv(csv: &Path) -> Res<CsvStats> {
+    let at = Location::file(csv);
+    let region_code = csv
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .ok_or("Missing Region")
+        .at(&at)?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(csv)
+        .at(&at)?;
+    let name_col = rdr
+        .byte_headers()
+        .at(&at)?
+        .iter()
+        .position(|h| h == b"Scientific Name with Author")
+        .unwrap_or(2);
+
+    let mut stats = CsvStats {
+        region_code,
+        file: csv.to_path_buf(),
+        rows: 0,
+        last_name: String::new(),
+        initials: BTreeSet::new(),
+    };
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record).at(&at)? {
+        stats.rows += 1;
+        if let Some(name) = record.get(name_col) {
+            let name = String::from_utf8_lossy(name).trim().to_string();
+            // hybrid genera start with a multiplication sign which is not a letter, skip them
+            if let Some(c) = name.chars().next().filter(char::is_ascii_alphabetic) {
+                stats.initials.insert(c.to_ascii_uppercase());
+                stats.last_name = name;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+fn initial(name: &str) -> Option<char> {
+    name.chars().next().map(|c| c.to_ascii_uppercase())
+}
+
+fn median<T: Copy + Ord>(values: &mut [T]) -> Option<T> {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+/// checks every region against the CSVs. `previous` holds the number of rows in the last import of each state, which
+/// is used as the manifest of what each file should look like. states that have never been imported are compared to
+/// the other states instead. the issues are returned keyed by region code.
+pub fn check(
+    stats: &[CsvStats],
+    region_map: &Map<String, Value>,
+    previous: &BTreeMap<String, i64>,
+) -> Vec<(String, Issue)> {
+    let mut issues = Vec::new();
+
+    for (code, name) in region_map {
+        if !stats
+            .iter()
+            .any(|s| s.region_code.eq_ignore_ascii_case(code))
+        {
+            let name = name.as_str().unwrap_or(code).to_string();
+            issues.push((code.clone(), Issue::MissingCsv { name }));
+        }
+    }
+
+    let median_rows = median(&mut stats.iter().map(|s| s.rows).collect::<Vec<_>>()).unwrap_or(0);
+    let expected_initial = median(
+        &mut stats
+            .iter()
+            .filter_map(|s| initial(&s.last_name))
+            .collect::<Vec<_>>(),
+    );
+
+    for s in stats {
+        let code = s.region_code.to_uppercase();
+        match previous.get(&code) {
+            Some(&prev) if (s.rows as f64) < prev as f64 * MIN_FRACTION_OF_PREVIOUS => {
+                issues.push((
+                    code.clone(),
+                    Issue::FewRows {
+                        rows: s.rows,
+                        expected: prev as usize,
+                        compared_to: "the last import of it",
+                    },
+                ))
+            }
+            Some(_) => {}
+            None if (s.rows as f64) < median_rows as f64 * MIN_FRACTION_OF_MEDIAN => issues.push((
+                code.clone(),
+                Issue::FewRows {
+                    rows: s.rows,
+                    expected: median_rows,
+                    compared_to: "the median state",
+                },
+            )),
+            None => {}
+        }
+
+        let last = initial(&s.last_name);
+        if let (Some(last), Some(expected)) = (last, expected_initial) {
+            if last < expected {
+                issues.push((
+                    code.clone(),
+                    Issue::EndsEarly {
+                        last_name: s.last_name.clone(),
+                        expected,
+                    },
+                ));
+            }
+        }
+
+        // only look for gaps in the part of the alphabet the file covers, a file that ends early is reported above
+        let others: Vec<&CsvStats> = stats
+            .iter()
+            .filter(|o| o.region_code != s.region_code)
+            .collect();
+        if others.len() >= 2 {
+            let missing: Vec<char> = ('A'..='Z')
+                .filter(|c| Some(*c) <= last && !s.initials.contains(c))
+                .filter(|c| others.iter().all(|o| o.initials.contains(c)))
+                .collect();
+            if !missing.is_empty() {
+                issues.push((code, Issue::MissingInitials(missing)));
+            }
+        }
+    }
+
+    issues
+}
+
+/// prints the issues so that they are hard to miss amongst the rest of the import output
+pub fn print_issues(issues: &[(String, Issue)]) {
+    if issues.is_empty() {
+        return;
+    }
+    eprintln!("********************************************************************************");
+    eprintln!("WARNING: the data for some regions looks incomplete:");
+    for (code, issue) in issues {
+        eprintln!("  {}: {}", code, issue);
+    }
+    eprintln!("********************************************************************************");
+}
+
+/// the error for a set of issues if any of them mean that the data that would be imported is incomplete
+pub fn incomplete_error(issues: &[(String, Issue)]) -> Option<ImporterError> {
+    let mut codes: Vec<&str> = issues
+        .iter()
+        .filter(|(_, i)| i.is_incomplete())
+        .map(|(c, _)| c.as_str())
+        .collect();
+    codes.dedup();
+    if codes.is_empty() {
+        None
+    } else {
+        Some(ImporterError::Incomplete {
+            at: Default::default(),
+            regions: codes.join(", "),
+        })
+    }
+}
+
+/// scans all of the CSVs and checks them against the regions and the last import of each state.
+pub fn validate_csvs(
+    csvs: &[PathBuf],
+    region_map: &Map<String, Value>,
+    previous: &BTreeMap<String, i64>,
+) -> Res<Vec<(String, Issue)>> {
+    let mut stats = Vec::new();
+    for csv in csvs {
+        stats.push(scan_csv(csv)?);
+    }
+    Ok(check(&stats, region_map, previous))
+}
+
+/// checks the CSVs in the data directory without importing anything. fails if any of them look incomplete.
+pub fn validate(paths: &Paths) -> Res<()> {
+    let parsed: Value = serde_json::from_str(&std::fs::read_to_string(&paths.regions)?)?;
+    let region_map: Map<String, Value> = parsed.as_object().unwrap().clone();
+    let mut csvs = Vec::new();
+    for csv_file in paths.data_dir.read_dir()? {
+        csvs.push(csv_file?.path());
+    }
+    csvs.sort();
+
+    // without an earlier import there is nothing to compare to but the other states
+    let previous = if paths.plants_db.is_file() {
+        let plant_c = Connection::open(&paths.plants_db)?;
+        let previous = PlantDB::new(&plant_c).select_latest_snapshot_rows()?;
+        previous
+    } else {
+        BTreeMap::new()
+    };
+    let issues = validate_csvs(&csvs, &region_map, &previous)?;
+
+    print_issues(&issues);
+    match incomplete_error(&issues) {
+        Some(e) => Err(e),
+        None => {
+            println!("Checked {} CSVs, none of them look incomplete.", csvs.len());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(code: &str, rows: usize, last_name: &str, initials: &str) -> CsvStats {
+        CsvStats {
+            region_code: code.to_string(),
+            file: PathBuf::from(format!("data/{}.csv", code)),
+            rows,
+            last_name: last_name.to_string(),
+            initials: initials.chars().collect(),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let region_map: Map<String, Value> = serde_json::from_str(
+            r#"{"DC": "District Of Columbia", "MN": "Minnesota", "WI": "Wisconsin", "IA": "Iowa", "ND": "North Dakota"}"#,
+        )
+        .unwrap();
+        let all = "ABCQVZ";
+        let csvs = vec![
+            stats("IA", 10640, "Zizia W.D.J. Koch", all),
+            stats("MN", 11492, "Viola renifolia A. Gray", "ABCQV"),
+            stats("ND", 3000, "Zizia W.D.J. Koch", all),
+            stats("WI", 12334, "Zizia W.D.J. Koch", "ABCVZ"),
+        ];
+        let mut previous = BTreeMap::new();
+        previous.insert("WI".to_string(), 14000);
+
+        let issues = check(&csvs, &region_map, &previous);
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "DC".to_string(),
+                    Issue::MissingCsv {
+                        name: "District Of Columbia".to_string()
+                    }
+                ),
+                (
+                    "MN".to_string(),
+                    Issue::EndsEarly {
+                        last_name: "Viola renifolia A. Gray".to_string(),
+                        expected: 'Z'
+                    }
+                ),
+                (
+                    "ND".to_string(),
+                    Issue::FewRows {
+                        rows: 3000,
+                        expected: 11492,
+                        compared_to: "the median state"
+                    }
+                ),
+                (
+                    "WI".to_string(),
+                    Issue::FewRows {
+                        rows: 12334,
+                        expected: 14000,
+                        compared_to: "the last import of it"
+                    }
+                ),
+                ("WI".to_string(), Issue::MissingInitials(vec!['Q'])),
+            ]
+        );
+
+        let e = incomplete_error(&issues).unwrap();
+        assert_eq!(e.exit_code(), 9);
+        assert!(incomplete_error(&issues[..1]).is_none());
+    }
+}
@@ -1,9 +1,18 @@
-use crate::gallformersdb::GallformersDB;
+use crate::backup::backup;
+use crate::cli::{ExportOptions, ImportOptions, Paths};
+use crate::exporttogf::remove_stale_places;
+use crate::gallformersdb::{GallformersDB, PlaceAssertion, PlaceSource};
+use crate::importcsvs::open_plants_db;
+use crate::nativity::{Nativity, Occurrence};
+use crate::plantdb::PlantDB;
+use crate::report::{Change, ExportReport, RegionMatch};
+use crate::species::Species;
 use crate::Res;
 use rusqlite::Connection;
 use serde_derive::Deserialize;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct TaxonomicAssertion {
     #[serde(rename = "acceptedNameUsage")]
@@ -22,6 +31,7 @@ struct TaxonomicAssertion {
     higher_classification: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct Location {
     #[serde(rename = "locationID")]
@@ -33,6 +43,7 @@ struct Location {
     occurrence_status: String,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct VernacularName {
     #[serde(rename = "vernacularName")]
@@ -43,6 +54,7 @@ struct VernacularName {
     preferred_name: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct VascanPlant {
     #[serde(rename = "taxonID")]
@@ -62,6 +74,7 @@ struct VascanPlant {
     distribution: Option<Vec<Location>>,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct VascanResult {
     #[serde(rename = "searchedTerm")]
@@ -71,6 +84,7 @@ struct VascanResult {
     matches: Option<Vec<VascanPlant>>,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct VascanResponse {
     #[serde(rename = "apiVersion")]
@@ -80,6 +94,42 @@ struct VascanResponse {
     results: Vec<VascanResult>,
 }
 
+/// a place in the VASCAN distribution of a gallformers host, as it was imported into the plants DB
+#[derive(Clone, Debug, PartialEq)]
+pub struct VascanDistribution {
+    /// the gallformers host that VASCAN was searched for
+    pub host: String,
+    pub taxon_id: u32,
+    pub scientific_name: String,
+    pub location_id: String,
+    /// the province or territory code, e.g., ON
+    pub locality: String,
+    pub establishment_means: String,
+    pub occurrence_status: String,
+    pub api_version: String,
+    pub last_updated: String,
+}
+
+impl VascanDistribution {
+    /// the parts of the VASCAN search result that put the host in the place, for recording where the range came from
+    fn record(&self) -> String {
+        format!(
+            "{} {}: {} {} {}",
+            self.taxon_id,
+            self.scientific_name,
+            self.location_id,
+            self.occurrence_status,
+            self.establishment_means
+        )
+    }
+
+    fn nativity(&self) -> Option<Nativity> {
+        // the occurrence status can also say whether it is native
+        Nativity::from_vascan(&self.establishment_means)
+            .or_else(|| Nativity::from_vascan(&self.occurrence_status))
+    }
+}
+
 fn handle_hybrid(s: &str) -> String {
     let v: Vec<&str> = s.split(" ").collect();
     let genus = v[0];
@@ -95,29 +145,52 @@ fn handle_hybrid(s: &str) -> String {
     )
 }
 
-pub async fn vascan_import() -> Res<()> {
-    let mut gf_db_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    gf_db_file.pop();
-    gf_db_file.push("prisma/gallformers.sqlite");
-    let gf_c = Connection::open(gf_db_file.as_path())?;
-    let mut gf_db = GallformersDB::new(&gf_c);
+pub async fn vascan_import(paths: &Paths, _options: &ImportOptions) -> Res<()> {
+    // the gallformers DB is only read, for the hosts to search VASCAN for
+    let gf_c = Connection::open(&paths.gf_db)?;
+    let plants = GallformersDB::new(&gf_c).select_all_plants()?;
 
+    let mut plant_c = open_plants_db(paths, &[])?;
     let client = reqwest::Client::builder().build()?;
 
-    gf_db.conn.execute_batch("BEGIN TRANSACTION;")?;
-    let plants = gf_db.select_all_plants()?;
-    let places = gf_db.select_places_by_type("province")?;
+    // the transaction is rolled back when it is dropped if anything fails before it is committed
+    let tx = plant_c.transaction()?;
+    let mut statuses = BTreeMap::new();
+    let total = {
+        let mut plant_db = PlantDB::new(&tx);
+        plant_db.delete_vascan_distribution()?;
+        add_vascan_distribution(&mut plant_db, &client, &plants, &mut statuses).await?
+    };
+    tx.commit()?;
+    println!("\nFinished processing {} plants.", total);
+    for (status, count) in statuses {
+        println!(
+            "Imported {} places with the occurrence status {}.",
+            count, status
+        );
+    }
+
+    Ok(())
+}
 
+/// stores every place that VASCAN puts each host in, whatever its occurrence status, so that the export can decide
+/// which count as range. the number of places with each status is added to `statuses`.
+async fn add_vascan_distribution(
+    plant_db: &mut PlantDB<'_>,
+    client: &reqwest::Client,
+    plants: &HashMap<String, Species>,
+    statuses: &mut BTreeMap<String, usize>,
+) -> Res<usize> {
     let mut count: usize = 0;
     let mut params = "".to_string();
     let total = plants.len();
 
-    for p in &plants {
-        count = count + 1;
-        if params.len() < 1 {
-            params = p.0.replace(" ", "%20");
+    for p in plants.keys() {
+        count += 1;
+        if params.is_empty() {
+            params = p.replace(" ", "%20");
         } else {
-            params = format!("{}%0A{}", params, p.0.replace(" ", "%20"));
+            params = format!("{}%0A{}", params, p.replace(" ", "%20"));
         }
         if count == 100 || count == total {
             let req_url = format!(
@@ -130,49 +203,37 @@ pub async fn vascan_import() -> Res<()> {
                 match r.matches {
                     Some(matches) => {
                         for result_plant in matches {
-                            match result_plant.distribution {
+                            match &result_plant.distribution {
                                 Some(distro) => {
+                                    let name = handle_hybrid(&result_plant.canonical_name);
+                                    if !plants.contains_key(&name) {
+                                        println!(
+                                            "Failed to find plant {}.",
+                                            result_plant.canonical_name
+                                        );
+                                        continue;
+                                    }
                                     for loc in distro {
-                                        if !loc.occurrence_status.eq_ignore_ascii_case("excluded")
-                                            && !loc
-                                                .occurrence_status
-                                                .eq_ignore_ascii_case("doubtful")
-                                        {
-                                            let name = handle_hybrid(&result_plant.canonical_name);
-                                            match plants.get(&name) {
-                                                Some(plant) => {
-                                                    let locality = match loc.locality.as_str() {
-                                                        "NL_N" | "NL_L" => "NL",
-                                                        // for whatever reason the Canadian data includes some for what appears to be Greenland
-                                                        "GL" => break,
-                                                        l => l,
-                                                    };
-                                                    match places.get(locality) {
-                                                        Some(region) => {
-                                                            // println!(
-                                                            //     "Adding {:?} for {:?}",
-                                                            //     region, plant
-                                                            // );
-                                                            gf_db.add_place_for_plant(
-                                                                plant.id, region.id,
-                                                            )?;
-                                                        }
-                                                        None => {
-                                                            println!(
-                                                                "Failed to find locality {}.",
-                                                                loc.locality
-                                                            );
-                                                        }
-                                                    };
-                                                }
-                                                None => {
-                                                    println!(
-                                                        "Failed to find plant {}.",
-                                                        result_plant.canonical_name
-                                                    );
-                                                }
-                                            }
-                                        }
+                                        plant_db.create_vascan_distribution(
+                                            &VascanDistribution {
+                                                host: name.clone(),
+                                                taxon_id: result_plant.taxon_id,
+                                                scientific_name: result_plant
+                                                    .scientific_name
+                                                    .clone(),
+                                                location_id: loc.location_id.clone(),
+                                                locality: loc.locality.clone(),
+                                                establishment_means: loc
+                                                    .establishment_means
+                                                    .clone(),
+                                                occurrence_status: loc.occurrence_status.clone(),
+                                                api_version: json.api_version.clone(),
+                                                last_updated: json.last_updated_date.clone(),
+                                            },
+                                        )?;
+                                        *statuses
+                                            .entry(loc.occurrence_status.to_lowercase())
+                                            .or_default() += 1;
                                     }
                                 }
                                 None => continue,
@@ -189,23 +250,255 @@ pub async fn vascan_import() -> Res<()> {
             params = "".to_string();
         }
     }
-    gf_db.conn.execute_batch("END TRANSACTION;")?;
-    println!("\nFinished processing {} plants.", total);
 
-    Ok(())
+    Ok(total)
 }
 
-pub async fn vascan_export() -> Res<()> {
+pub async fn vascan_export(paths: &Paths, options: &ExportOptions) -> Res<()> {
+    let range = if options.occurrence.is_empty() {
+        Occurrence::RANGE.to_vec()
+    } else {
+        options.occurrence.clone()
+    };
+
+    let mut gf_c = Connection::open(&paths.gf_db)?;
+    if !options.dry_run {
+        backup(&gf_c, &paths.gf_db, &paths.backup_dir)?;
+    }
+
+    let plant_c = Connection::open(&paths.plants_db)?;
+    let mut plant_db = PlantDB::new(&plant_c);
+
+    // as for the USDA export, a dry run rolls the transaction back so that the report reflects exactly what would happen
+    let tx = gf_c.transaction()?;
+    let mut skipped = BTreeMap::new();
+    let report = export_vascan_places(
+        &mut GallformersDB::new(&tx),
+        &mut plant_db,
+        &range,
+        options.sync,
+        &mut skipped,
+    )?;
+
+    if options.dry_run {
+        tx.rollback()?;
+        report.print();
+        println!("Dry run, nothing was written to {:?}.", paths.gf_db);
+    } else {
+        tx.commit()?;
+        report.print_summary();
+    }
+    for (status, count) in skipped {
+        println!(
+            "Skipped {} places with the occurrence status {}.",
+            count, status
+        );
+    }
+
+    if let Some(csv) = &options.report {
+        report.write_csv(csv)?;
+        println!("Wrote export report to {:?}.", csv);
+    }
+
     Ok(())
 }
 
+/// adds the places that the latest VASCAN import puts each host in. only the places with one of the `range` occurrence
+/// statuses are added, the number of places skipped for each of the other statuses is added to `skipped`. with `sync`
+/// the VASCAN source is also removed from the links that are no longer in range, see `remove_stale_places`.
+fn export_vascan_places(
+    gf_db: &mut GallformersDB,
+    plant_db: &mut PlantDB,
+    range: &[Occurrence],
+    sync: bool,
+    skipped: &mut BTreeMap<String, usize>,
+) -> Res<ExportReport> {
+    let mut report = ExportReport::new();
+    // every range link that the VASCAN data supports, so that a sync can remove the rest
+    let mut asserted = HashSet::new();
+    let plants = gf_db.select_all_plants()?;
+    let places = gf_db.select_places_by_type("province")?;
+
+    for d in plant_db.select_vascan_distribution()? {
+        let occurrence = match d.occurrence_status.parse::<Occurrence>() {
+            Ok(o) if range.contains(&o) => o,
+            _ => {
+                *skipped
+                    .entry(d.occurrence_status.to_lowercase())
+                    .or_default() += 1;
+                continue;
+            }
+        };
+        let plant = match plants.get(&d.host) {
+            Some(plant) => plant,
+            None => {
+                println!("Failed to find plant {}.", d.host);
+                continue;
+            }
+        };
+        let locality = match d.locality.as_str() {
+            "NL_N" | "NL_L" => "NL",
+            // for whatever reason the Canadian data includes some for what appears to be Greenland
+            "GL" => continue,
+            l => l,
+        };
+        match places.get(locality) {
+            Some(region) => {
+                asserted.insert((plant.id, region.id));
+                let added = gf_db.assert_place_for_plant(
+                    plant.id,
+                    region.id,
+                    &PlaceAssertion {
+                        source: PlaceSource::Vascan,
+                        version: &d.api_version,
+                        asof: &d.last_updated,
+                        record: &d.record(),
+                        nativity: d.nativity(),
+                        occurrence: Some(occurrence),
+                    },
+                )?;
+                report.species_place(
+                    &plant.name,
+                    &region.name,
+                    Change::from_inserted(added),
+                    RegionMatch::Exact,
+                );
+            }
+            None => {
+                println!("Failed to find locality {}.", d.locality);
+            }
+        };
+    }
+
+    if sync {
+        remove_stale_places(gf_db, &mut report, PlaceSource::Vascan, &asserted)?;
+    }
+
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::Cli;
+    use crate::exporttogf::export;
+    use crate::gallformersdb::tests::gf_conn;
+    use clap::Parser;
 
     #[test]
     fn test_handle_hybrid() {
         assert_eq!(handle_hybrid("Quercus alba"), "Quercus alba");
         assert_eq!(handle_hybrid("Quercus ×leana"), "Quercus x leana");
     }
+
+    #[tokio::test]
+    async fn test_export_usda_rejects_occurrence() {
+        let paths = Cli::try_parse_from(["importer", "snapshots"])
+            .unwrap()
+            .paths;
+        let options = ExportOptions {
+            occurrence: vec![Occurrence::Native],
+            ..Default::default()
+        };
+        assert!(export(&paths, &options).await.is_err());
+    }
+
+    fn distribution(locality: &str, occurrence: &str) -> VascanDistribution {
+        VascanDistribution {
+            host: "Quercus alba".to_string(),
+            taxon_id: 1,
+            scientific_name: "Quercus alba Linnaeus".to_string(),
+            location_id: format!("ISO 3166-2:CA-{}", locality),
+            locality: locality.to_string(),
+            establishment_means: "".to_string(),
+            occurrence_status: occurrence.to_string(),
+            api_version: "0.1".to_string(),
+            last_updated: "2021-10-18".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_export_filters_occurrence() {
+        let plant_c = Connection::open_in_memory().unwrap();
+        plant_c
+            .execute_batch(include_str!("../plants.sql"))
+            .unwrap();
+        let mut plant_db = PlantDB::new(&plant_c);
+        // every status is imported
+        for (locality, occurrence) in [
+            ("ON", "native"),
+            ("QC", "doubtful"),
+            ("NB", "extirpated"),
+            ("GL", "native"),
+        ] {
+            plant_db
+                .create_vascan_distribution(&distribution(locality, occurrence))
+                .unwrap();
+        }
+        assert_eq!(plant_db.select_vascan_distribution().unwrap().len(), 4);
+
+        let gf_c = gf_conn();
+        gf_c.execute_batch(
+            "INSERT INTO place VALUES (5, 'Quebec', 'QC', 'province'), (6, 'New Brunswick', 'NB', 'province');",
+        )
+        .unwrap();
+        let mut gf_db = GallformersDB::new(&gf_c);
+        let occurrences = |gf_c: &Connection| -> Vec<(i64, String)> {
+            let mut stmt = gf_c
+                .prepare("SELECT place_id, occurrence FROM speciesplace WHERE occurrence IS NOT NULL ORDER BY place_id;")
+                .unwrap();
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+            rows.map(|r| r.unwrap()).collect()
+        };
+
+        // by default only the statuses that say the plant is there now are range
+        let mut skipped = BTreeMap::new();
+        let report = export_vascan_places(
+            &mut gf_db,
+            &mut plant_db,
+            &Occurrence::RANGE,
+            false,
+            &mut skipped,
+        )
+        .unwrap();
+        assert_eq!(
+            report.species["Quercus alba"].places["Ontario"],
+            (Change::Add, RegionMatch::Exact)
+        );
+        assert_eq!(report.species["Quercus alba"].places.len(), 1);
+        assert_eq!(skipped["doubtful"], 1);
+        assert_eq!(skipped["extirpated"], 1);
+        assert_eq!(occurrences(&gf_c), vec![(4, "native".to_string())]);
+
+        // a later export can choose other statuses without importing again
+        let mut skipped = BTreeMap::new();
+        export_vascan_places(
+            &mut gf_db,
+            &mut plant_db,
+            &[Occurrence::Native, Occurrence::Extirpated],
+            false,
+            &mut skipped,
+        )
+        .unwrap();
+        assert_eq!(skipped["doubtful"], 1);
+        assert_eq!(
+            occurrences(&gf_c),
+            vec![(4, "native".to_string()), (6, "extirpated".to_string())]
+        );
+
+        // and a sync with the narrower default removes the extirpated link that the wider one added
+        let report = export_vascan_places(
+            &mut gf_db,
+            &mut plant_db,
+            &Occurrence::RANGE,
+            true,
+            &mut BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.species["Quercus alba"].removed["New Brunswick"],
+            ("vascan".to_string(), vec![])
+        );
+        assert_eq!(occurrences(&gf_c), vec![(4, "native".to_string())]);
+    }
 }